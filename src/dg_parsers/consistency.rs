@@ -0,0 +1,42 @@
+use crate::dg_parsers::helpers as dg_helpers;
+use crate::types;
+use simplelog::{info, warn};
+
+impl types::ConsistencyReport {
+    #[cfg(feature = "cli")]
+    pub fn fancy_print(&self) {
+        info!("");
+        info!("{}", dg_helpers::pad_section_title("Consistency"));
+        info!(
+            "{}",
+            dg_helpers::pad_section_subtitle("Cross-checks between MRZ and data groups")
+        );
+        info!("");
+        if self.checks_performed.is_empty() {
+            warn!("Not enough data was read to perform any cross-checks.");
+            info!("");
+            return;
+        }
+        dg_helpers::print_string_element("Checks performed", &self.checks_performed.join(", "));
+        for finding in self.findings.iter() {
+            let note = match finding.note.as_ref() {
+                Some(note) => format!(" {}", note),
+                None => "".to_string(),
+            };
+            match finding.severity {
+                types::ConsistencyFindingSeverity::Mismatch => warn!(
+                    "<red>Mismatch</> ({}) in {}: \"{}\" vs \"{}\".{}",
+                    finding.check, finding.field, finding.first_value, finding.second_value, note
+                ),
+                types::ConsistencyFindingSeverity::Notice => info!(
+                    "<yellow>Notice</> ({}) in {}: \"{}\" vs \"{}\".{}",
+                    finding.check, finding.field, finding.first_value, finding.second_value, note
+                ),
+            }
+        }
+        if self.findings.is_empty() {
+            info!("<green>No inconsistencies found.</>");
+        }
+        info!("");
+    }
+}
@@ -0,0 +1,181 @@
+use crate::dg_parsers::helpers as dg_helpers;
+use crate::helpers;
+use crate::iso7816;
+use crate::types;
+use iso7816_tlv::ber;
+use simplelog::{debug, info, warn};
+
+impl types::EFAtrInfo {
+    #[cfg(feature = "cli")]
+    pub fn fancy_print(&self, data_group: &types::DataGroup) {
+        dg_helpers::print_section_intro(data_group);
+        dg_helpers::print_option_binary_element(
+            "Card service data",
+            &self.card_service_data.map(|byte| vec![byte]),
+        );
+        dg_helpers::print_option_binary_element("Card capabilities", &self.card_capabilities);
+        dg_helpers::print_string_element("Command chaining", &self.command_chaining.to_string());
+        dg_helpers::print_string_element("Extended length", &self.extended_length.to_string());
+        match self.max_command_len {
+            Some(max_command_len) => dg_helpers::print_string_element(
+                "Max command length",
+                &format!("{}b", max_command_len),
+            ),
+            None => {}
+        }
+        match self.max_response_len {
+            Some(max_response_len) => dg_helpers::print_string_element(
+                "Max response length",
+                &format!("{}b", max_response_len),
+            ),
+            None => {}
+        }
+        info!("");
+    }
+
+    /// Returns the APDU sizes the chip says it can handle.
+    pub fn apdu_limits(&self) -> iso7816::ApduLimits {
+        return iso7816::ApduLimits {
+            extended_length: self.extended_length,
+            max_command_len: self.max_command_len,
+            max_response_len: self.max_response_len,
+        };
+    }
+}
+
+/// Parses compact-TLV data objects, as used in historical bytes, into (BER-TLV tag, value).
+///
+/// A compact tag N is the interindustry BER-TLV tag 4N, like 73 being card capabilities (47).
+fn parse_compact_tlvs(data: &[u8]) -> Option<Vec<(u16, Vec<u8>)>> {
+    let mut data_objects: Vec<(u16, Vec<u8>)> = vec![];
+    let mut offset = 0;
+    while offset < data.len() {
+        let tag = 0x40 | (data[offset] >> 4) as u16;
+        let len = (data[offset] & 0x0F) as usize;
+        let value = data.get(offset + 1..offset + 1 + len)?;
+        data_objects.push((tag, value.to_vec()));
+        offset += 1 + len;
+    }
+    return Some(data_objects);
+}
+
+pub fn parser(
+    data: &Vec<u8>,
+    data_group: &types::DataGroup,
+    print_data: bool,
+) -> Option<types::ParsedDataGroup> {
+    // The data objects are usually BER-TLV, but some chips use compact-TLV like in the ATR.
+    let data_objects: Vec<(u16, ber::Value)> = match dg_helpers::parse_tlvs(data) {
+        Some(tlvs) => tlvs
+            .iter()
+            .map(|tlv| (helpers::get_tlv_tag(tlv), tlv.value().clone()))
+            .collect(),
+        None => match parse_compact_tlvs(data) {
+            Some(data_objects) => data_objects
+                .into_iter()
+                .map(|(tag, value)| (tag, ber::Value::Primitive(value)))
+                .collect(),
+            None => {
+                warn!(
+                    "Couldn't parse {} as BER-TLV or compact-TLV.",
+                    data_group.name
+                );
+                return None;
+            }
+        },
+    };
+    debug!("data_objects: {:02x?}", data_objects);
+
+    let mut result = types::EFAtrInfo {
+        card_service_data: None,
+        card_capabilities: None,
+        command_chaining: false,
+        extended_length: false,
+        max_command_len: None,
+        max_response_len: None,
+    };
+    for (tag, value) in data_objects.into_iter() {
+        match (tag, value) {
+            (0x43, ber::Value::Primitive(value)) => {
+                result.card_service_data = value.first().copied();
+            }
+            (0x47, ber::Value::Primitive(value)) => {
+                // Third software function table: b8 is command chaining, b7 is extended Lc and Le
+                match value.get(2) {
+                    Some(table) => {
+                        result.command_chaining = table & 0x80 != 0;
+                        result.extended_length |= table & 0x40 != 0;
+                    }
+                    None => {}
+                }
+                result.card_capabilities = Some(value);
+            }
+            (0x7F66, ber::Value::Constructed(tlvs)) => {
+                // Extended length information: max command and response APDU lengths
+                let lengths: Vec<usize> = helpers::get_tlvs_by_tag(&tlvs, 0x02)
+                    .iter()
+                    .filter_map(|tlv| dg_helpers::tlv_get_integer(tlv))
+                    .filter_map(|len| usize::try_from(len).ok())
+                    .collect();
+                result.max_command_len = lengths.first().copied();
+                result.max_response_len = lengths.get(1).copied();
+                result.extended_length = true;
+            }
+            (tag, _) => debug!("Skipping unknown data object {:04X}", tag),
+        }
+    }
+
+    if print_data {
+        #[cfg(feature = "cli")]
+        result.fancy_print(data_group);
+    }
+    return Some(types::ParsedDataGroup::EFAtrInfo(result));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ef_atr_info_parsing() {
+        let data_group = &types::DATA_GROUPS[types::DataGroupEnum::EFAtrInfo as usize];
+        let parse = |data: Vec<u8>| match parser(&data, data_group, false) {
+            Some(types::ParsedDataGroup::EFAtrInfo(ef_atr_info_file)) => ef_atr_info_file,
+            parsed_data => panic!("Expected EFAtrInfo but got {:x?}", parsed_data),
+        };
+
+        // Card capabilities with chaining and extended length, and 1000/1792 byte buffers
+        let ef_atr_info_file = parse(vec![
+            0x47, 0x03, 0x00, 0x00, 0xC0, 0x7F, 0x66, 0x08, 0x02, 0x02, 0x03, 0xE8, 0x02, 0x02,
+            0x07, 0x00,
+        ]);
+        assert!(ef_atr_info_file.command_chaining);
+        assert!(ef_atr_info_file.extended_length);
+        assert_eq!(ef_atr_info_file.max_command_len, Some(1000));
+        assert_eq!(ef_atr_info_file.max_response_len, Some(1792));
+
+        // Compact-TLV card service data and card capabilities without extended length
+        let ef_atr_info_file = parse(vec![0x31, 0xC1, 0x73, 0x00, 0x00, 0x80]);
+        assert_eq!(ef_atr_info_file.card_service_data, Some(0xC1));
+        assert!(ef_atr_info_file.command_chaining);
+        assert!(!ef_atr_info_file.extended_length);
+        assert_eq!(ef_atr_info_file.max_response_len, None);
+
+        // 256 byte responses fit 231 bytes of data with secure messaging
+        let mut apdu_limits = iso7816::ApduLimits {
+            extended_length: false,
+            max_command_len: Some(1000),
+            max_response_len: Some(1792),
+        };
+        assert_eq!(apdu_limits.read_chunk_len(true, true), 0xE7);
+        assert_eq!(apdu_limits.read_chunk_len(false, true), 0x100);
+        // With extended lengths, secure messaging takes up 22 of the 1790 bytes
+        apdu_limits.extended_length = true;
+        assert_eq!(apdu_limits.read_chunk_len(true, true), 1767);
+        assert_eq!(apdu_limits.read_chunk_len(true, false), 0xE7);
+        assert_eq!(
+            iso7816::ApduLimits::default().read_chunk_len(true, true),
+            0xDF
+        );
+    }
+}
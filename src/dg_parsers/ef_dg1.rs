@@ -41,6 +41,34 @@ impl types::TD1Mrz {
     }
 }
 
+impl types::TD2Mrz {
+    #[cfg(feature = "cli")]
+    pub fn fancy_print(&self) {
+        dg_helpers::print_string_element("Document Size", &"TD2".to_string());
+        dg_helpers::print_string_element(
+            "Document Type",
+            &dg_helpers::parse_mrz_document_code(&self.document_code, &self.issuing_state),
+        );
+        dg_helpers::print_string_element("Issuing State", &self.issuing_state);
+        dg_helpers::print_string_element_as_name("Name of Holder", &self.name_of_holder);
+        dg_helpers::print_string_element("Document Number", &self.document_number);
+        dg_helpers::print_string_element("Nationality", &self.nationality);
+        dg_helpers::print_string_element_as_mrz_date("Date of Birth", &self.date_of_birth);
+        dg_helpers::print_string_element("Legal Sex Marker", &dg_helpers::parse_mrz_sex(self.sex));
+        dg_helpers::print_string_element_as_mrz_date("Date of Expiry", &self.date_of_expiry);
+        if self.optional_data_elements.len() != 0 {
+            dg_helpers::print_string_element("Optional elements", &self.optional_data_elements);
+        }
+        let checksum_result = self.validate_check_digits(true);
+        let checksum_text = match checksum_result.iter().all(|&val| val == true) {
+            true => "</><green>All valid!</>",
+            false => "</><red>Mismatches found!</>",
+        }
+        .to_string();
+        dg_helpers::print_string_element("MRZ Checksums", &checksum_text);
+    }
+}
+
 impl types::TD3Mrz {
     #[cfg(feature = "cli")]
     pub fn fancy_print(&self) {
@@ -70,12 +98,43 @@ impl types::TD3Mrz {
     }
 }
 
+impl types::MRVMrz {
+    #[cfg(feature = "cli")]
+    pub fn fancy_print(&self) {
+        let document_size = match self.is_mrv_a() {
+            true => "MRV-A (Visa, Passport-size)",
+            false => "MRV-B (Visa, TD2-size)",
+        };
+        dg_helpers::print_string_element("Document Size", &document_size.to_string());
+        dg_helpers::print_string_element("Document Type", &"Visa".to_string());
+        dg_helpers::print_string_element("Issuing State", &self.issuing_state);
+        dg_helpers::print_string_element_as_name("Name of Holder", &self.name_of_holder);
+        dg_helpers::print_string_element("Visa Number", &self.document_number);
+        dg_helpers::print_string_element("Nationality", &self.nationality);
+        dg_helpers::print_string_element_as_mrz_date("Date of Birth", &self.date_of_birth);
+        dg_helpers::print_string_element("Legal Sex Marker", &dg_helpers::parse_mrz_sex(self.sex));
+        dg_helpers::print_string_element_as_mrz_date("Valid Until", &self.date_of_expiry);
+        if self.optional_data_elements.len() != 0 {
+            dg_helpers::print_string_element("Optional elements", &self.optional_data_elements);
+        }
+        let checksum_result = self.validate_check_digits(true);
+        let checksum_text = match checksum_result.iter().all(|&val| val == true) {
+            true => "</><green>All valid!</>",
+            false => "</><red>Mismatches found!</>",
+        }
+        .to_string();
+        dg_helpers::print_string_element("MRZ Checksums", &checksum_text);
+    }
+}
+
 impl types::MRZ {
     #[cfg(feature = "cli")]
     pub fn fancy_print(&self) {
         match self {
             Self::TD1(mrzobj) => mrzobj.fancy_print(),
+            Self::TD2(mrzobj) => mrzobj.fancy_print(),
             Self::TD3(mrzobj) => mrzobj.fancy_print(),
+            Self::MRVA(mrzobj) | Self::MRVB(mrzobj) => mrzobj.fancy_print(),
         }
     }
 }
@@ -9,7 +9,7 @@ impl types::EFDG11 {
     pub fn fancy_print(&self, data_group: &types::DataGroup) {
         dg_helpers::print_section_intro(data_group);
         dg_helpers::print_option_string_element_as_name("Full name of holder", &self.full_name);
-        dg_helpers::print_option_debug_element("Other names", &self.other_names);
+        dg_helpers::print_option_name_list_element("Other name", &self.other_names);
         dg_helpers::print_option_string_element("Personal number", &self.personal_number);
         dg_helpers::print_option_string_element_as_dg_date(
             "Full date of birth",
@@ -57,7 +57,7 @@ pub fn parser(
     // Deserialize the file from the given TLV data.
     let result = types::EFDG11 {
         full_name: dg_helpers::tlv_get_string_value(&tlvs, &0x5F0E),
-        other_names: None, // TODO: impl this
+        other_names: dg_helpers::parse_name_list(&base_tlv_value, 0x5F0F),
         personal_number: dg_helpers::tlv_get_string_value(&tlvs, &0x5F10),
         full_date_of_birth: dg_helpers::tlv_get_string_value(&tlvs, &0x5F2B),
         place_of_birth: dg_helpers::tlv_get_string_value(&tlvs, &0x5F11),
@@ -10,7 +10,7 @@ impl types::EFDG12 {
         dg_helpers::print_section_intro(data_group);
         dg_helpers::print_option_string_element("Issuing Authority", &self.issuing_authority);
         dg_helpers::print_option_string_element_as_dg_date("Date of issue", &self.date_of_issue);
-        dg_helpers::print_option_debug_element("Other persons", &self.other_persons);
+        dg_helpers::print_option_name_list_element("Other person", &self.other_persons);
         dg_helpers::print_option_string_element(
             "Endorsements/Observations",
             &self.endorsements_observations,
@@ -72,7 +72,7 @@ pub fn parser(
         personalization_device_serial_number: dg_helpers::tlv_get_string_value(&tlvs, &0x5F56),
         image_of_front_of_emrtd: dg_helpers::tlv_get_bytes(&tlvs, &0x5F1D),
         image_of_rear_of_emrtd: dg_helpers::tlv_get_bytes(&tlvs, &0x5F1D),
-        other_persons: None, // TODO: impl this
+        other_persons: dg_helpers::parse_name_list(&base_tlv_value, 0x5F1A),
     };
     if print_data {
         #[cfg(feature = "cli")]
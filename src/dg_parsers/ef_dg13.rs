@@ -0,0 +1,241 @@
+use crate::dg_parsers::helpers as dg_helpers;
+use crate::helpers;
+use crate::types;
+use iso7816_tlv::ber;
+use simplelog::{debug, info, warn};
+
+/// A decoder for a DG13 layout.
+pub(crate) struct DG13Decoder {
+    pub name: &'static str,
+    /// Issuing states (as in the MRZ) that use this layout, empty if it can be tried for any state
+    pub issuing_states: &'static [&'static str],
+    /// Whether the layout is a guess that wasn't checked against real documents of these states
+    pub speculative: bool,
+    /// Decodes the TLVs inside DG13's base TLV, None if they don't match the layout
+    pub decode: fn(&Vec<ber::Tlv>) -> Option<Vec<(&'static str, String)>>,
+}
+
+/// Known DG13 layouts. Ones specific to an issuing state are tried before generic ones.
+pub(crate) static DG13_DECODERS: &[DG13Decoder] = &[
+    DG13Decoder {
+        name: "Birth name",
+        issuing_states: &["D"],
+        // Modelled on the German eID application, which isn't the eMRTD's DG13.
+        speculative: true,
+        decode: decode_birth_name,
+    },
+    DG13Decoder {
+        name: "ICAO 9303 data elements",
+        issuing_states: &[],
+        speculative: false,
+        decode: decode_icao_data_elements,
+    },
+];
+
+/// A single UTF8String holding the birth name, like DG13 of the German eID application (BSI TR-03110 part 4)
+///
+/// This is a guess for the eMRTD's DG13, as no real German passport DG13 was checked against it.
+fn decode_birth_name(tlvs: &Vec<ber::Tlv>) -> Option<Vec<(&'static str, String)>> {
+    if tlvs.len() != 1 || helpers::get_tlv_tag(&tlvs[0]) != 0x0C {
+        return None;
+    }
+    let value = match tlvs[0].value() {
+        ber::Value::Primitive(data) => String::from_utf8(data.clone()).ok()?,
+        ber::Value::Constructed(_) => return None,
+    };
+    return Some(vec![("Birth name", value)]);
+}
+
+/// Data elements with the tags ICAO 9303 uses for DG11, DG12 and DG16, which some issuers reuse.
+fn decode_icao_data_elements(tlvs: &Vec<ber::Tlv>) -> Option<Vec<(&'static str, String)>> {
+    if tlvs.is_empty() {
+        return None;
+    }
+    let mut fields: Vec<(&'static str, String)> = vec![];
+    for tlv in tlvs.iter() {
+        let name = match helpers::get_tlv_tag(tlv) {
+            0x5F0E => "Full name",
+            0x5F0F => "Other name",
+            0x5F10 => "Personal number",
+            0x5F11 => "Place of birth",
+            0x5F12 => "Telephone",
+            0x5F13 => "Profession",
+            0x5F14 => "Title",
+            0x5F15 => "Personal summary",
+            0x5F17 => "Other valid TD numbers",
+            0x5F18 => "Custody information",
+            0x5F19 => "Issuing authority",
+            0x5F1A => "Other person",
+            0x5F1B => "Endorsements/Observations",
+            0x5F1C => "Tax/Exit requirements",
+            0x5F26 => "Date of issue",
+            0x5F2B => "Full date of birth",
+            0x5F42 => "Address",
+            0x5F55 => "Personalization timestamp",
+            0x5F56 => "Personalization device serial number",
+            _ => return None,
+        };
+        let value = match tlv.value() {
+            ber::Value::Primitive(data) => String::from_utf8(data.clone()).ok()?,
+            ber::Value::Constructed(_) => return None,
+        };
+        fields.push((name, value));
+    }
+    return Some(fields);
+}
+
+impl types::EFDG13 {
+    /// Decodes the fields with the first matching layout for the issuing state, if it's known.
+    pub fn decode_fields(&mut self, issuing_state: Option<&str>) {
+        self.layout = None;
+        self.fields = vec![];
+        let base_tlv = match ber::Tlv::parse(&self.data).0 {
+            Ok(base_tlv) => base_tlv,
+            Err(_) => return,
+        };
+        let base_tlv_value = match dg_helpers::tlv_get_constructed_value(&base_tlv) {
+            Some(base_tlv_value) => base_tlv_value,
+            None => return,
+        };
+
+        let state_decoders = DG13_DECODERS.iter().filter(|decoder| {
+            decoder
+                .issuing_states
+                .iter()
+                .any(|state| Some(*state) == issuing_state)
+        });
+        let generic_decoders = DG13_DECODERS
+            .iter()
+            .filter(|decoder| decoder.issuing_states.is_empty());
+        for decoder in state_decoders.chain(generic_decoders) {
+            match (decoder.decode)(&base_tlv_value) {
+                Some(fields) => {
+                    debug!("Decoded DG13 as {}", decoder.name);
+                    self.layout = Some(decoder.name);
+                    self.fields = fields;
+                    return;
+                }
+                None => {}
+            }
+        }
+    }
+
+    #[cfg(feature = "cli")]
+    pub fn fancy_print(&self, data_group: &types::DataGroup) {
+        dg_helpers::print_section_intro(data_group);
+        let speculative = DG13_DECODERS
+            .iter()
+            .any(|decoder| Some(decoder.name) == self.layout && decoder.speculative);
+        match self.layout {
+            Some(layout) if speculative => {
+                dg_helpers::print_string_element(
+                    "Layout",
+                    &format!("{} (unconfirmed guess)", layout),
+                );
+                for (name, value) in self.fields.iter() {
+                    dg_helpers::print_string_element(name, value);
+                }
+                // Show the structure too, so the guess can be checked.
+                self.print_tlv_tree();
+            }
+            Some(layout) => {
+                dg_helpers::print_string_element("Layout", &layout.to_string());
+                for (name, value) in self.fields.iter() {
+                    dg_helpers::print_string_element(name, value);
+                }
+            }
+            None => {
+                info!(
+                    "{:^pad_len$}",
+                    "<b>(Unknown layout, showing TLV structure)</>",
+                    // + 6 for bold
+                    pad_len = dg_helpers::SECTION_TITLE_PAD_TO_LEN + 6
+                );
+                self.print_tlv_tree();
+            }
+        }
+        info!("");
+    }
+
+    #[cfg(feature = "cli")]
+    fn print_tlv_tree(&self) {
+        match dg_helpers::parse_tlvs(&self.data) {
+            Some(tlvs) => dg_helpers::print_tlv_tree(&tlvs, 0),
+            None => dg_helpers::print_option_binary_element(
+                &format!("Raw data ({}b)", self.data.len()),
+                &Some(&self.data),
+            ),
+        }
+    }
+}
+
+/// Parses DG13 with the generic layouts, as the issuing state isn't known here.
+///
+/// Use `decode_fields` to decode it with the layouts of the issuing state.
+pub fn parser(
+    data: &Vec<u8>,
+    data_group: &types::DataGroup,
+    print_data: bool,
+) -> Option<types::ParsedDataGroup> {
+    // Parse the base TLV
+    let base_tlv = ber::Tlv::parse(data).0.ok()?;
+    debug!("base_tlv: {:02x?}", &base_tlv);
+
+    let base_tlv_tag = helpers::get_tlv_tag(&base_tlv);
+    if base_tlv_tag != data_group.tag.into() {
+        warn!(
+            "Found {}'s TLV tag as 0x{} (expected 0x{}), skipping parsing.",
+            data_group.name, base_tlv_tag, data_group.tag
+        );
+        return None;
+    };
+
+    let mut result = types::EFDG13 {
+        data: data.clone(),
+        layout: None,
+        fields: vec![],
+    };
+    result.decode_fields(None);
+    if print_data {
+        #[cfg(feature = "cli")]
+        result.fancy_print(data_group);
+    }
+    return Some(types::ParsedDataGroup::EFDG13(result));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dg13_layout_decoding() {
+        // 6D -> 0C "MUSTERMANN"
+        let mut ef_dg13 = types::EFDG13 {
+            data: [&[0x6D, 0x0C, 0x0C, 0x0A][..], b"MUSTERMANN"].concat(),
+            layout: None,
+            fields: vec![],
+        };
+        ef_dg13.decode_fields(Some("D"));
+        assert_eq!(ef_dg13.layout, Some("Birth name"));
+        assert_eq!(
+            ef_dg13.fields,
+            vec![("Birth name", "MUSTERMANN".to_string())]
+        );
+        // Other states don't use this layout
+        ef_dg13.decode_fields(Some("UTO"));
+        assert_eq!(ef_dg13.layout, None);
+
+        // 6D -> 5F10 "1234"
+        let mut ef_dg13 = types::EFDG13 {
+            data: [&[0x6D, 0x07, 0x5F, 0x10, 0x04][..], b"1234"].concat(),
+            layout: None,
+            fields: vec![],
+        };
+        ef_dg13.decode_fields(Some("UTO"));
+        assert_eq!(ef_dg13.layout, Some("ICAO 9303 data elements"));
+        assert_eq!(
+            ef_dg13.fields,
+            vec![("Personal number", "1234".to_string())]
+        );
+    }
+}
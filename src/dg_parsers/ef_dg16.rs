@@ -0,0 +1,153 @@
+use crate::dg_parsers::helpers as dg_helpers;
+use crate::helpers;
+use crate::types;
+use iso7816_tlv::ber;
+use simplelog::{debug, info, warn};
+
+impl types::EFDG16 {
+    #[cfg(feature = "cli")]
+    pub fn fancy_print(&self, data_group: &types::DataGroup) {
+        dg_helpers::print_section_intro(data_group);
+        for (i, person) in self.persons_to_notify.iter().enumerate() {
+            info!(
+                "{:^pad_len$}",
+                format!("<b><u>Person #{}</>", i + 1),
+                // + 9 for b, u and /
+                pad_len = dg_helpers::SECTION_TITLE_PAD_TO_LEN + 9
+            );
+            dg_helpers::print_option_string_element_as_dg_date(
+                "Date data recorded",
+                &person.date_recorded,
+            );
+            dg_helpers::print_option_string_element_as_name("Name", &person.name);
+            dg_helpers::print_option_string_element("Telephone", &person.telephone);
+            dg_helpers::print_option_string_element("Address", &person.address);
+        }
+        info!("");
+    }
+}
+
+pub fn parser(
+    data: &Vec<u8>,
+    data_group: &types::DataGroup,
+    print_data: bool,
+) -> Option<types::ParsedDataGroup> {
+    // Parse the base TLV
+    let base_tlv = ber::Tlv::parse(data).0.ok()?;
+    debug!("base_tlv: {:02x?}", &base_tlv);
+
+    let base_tlv_tag = helpers::get_tlv_tag(&base_tlv);
+    if base_tlv_tag != data_group.tag.into() {
+        warn!(
+            "Found {}'s TLV tag as 0x{} (expected 0x{}), skipping parsing.",
+            data_group.name, base_tlv_tag, data_group.tag
+        );
+        return None;
+    };
+
+    // 70 -> 02 (number of persons), A1, A2... (one template per person)
+    let base_tlv_value = helpers::get_tlv_constructed_value(&base_tlv);
+    let mut persons_to_notify: Vec<types::PersonToNotify> = vec![];
+    for template in base_tlv_value.iter() {
+        let template_tag = helpers::get_tlv_tag(template);
+        if !(0xA1..=0xBE).contains(&template_tag) {
+            continue;
+        }
+        let template_value = match dg_helpers::tlv_get_constructed_value(template) {
+            Some(template_value) => template_value,
+            None => {
+                warn!("Found a primitive person to notify template, skipping it.");
+                continue;
+            }
+        };
+        let tlvs = helpers::sort_tlvs_by_tag(&template_value);
+        debug!("tlvs: {:02x?}", tlvs);
+        persons_to_notify.push(types::PersonToNotify {
+            date_recorded: dg_helpers::tlv_get_string_value(&tlvs, &0x5F50),
+            name: dg_helpers::tlv_get_string_value(&tlvs, &0x5F51),
+            telephone: dg_helpers::tlv_get_string_value(&tlvs, &0x5F52),
+            address: dg_helpers::tlv_get_string_value(&tlvs, &0x5F53),
+        });
+    }
+
+    match helpers::get_tlv_by_tag(&base_tlv_value, 0x02).and_then(dg_helpers::tlv_get_integer) {
+        Some(count) if count != persons_to_notify.len() as i64 => warn!(
+            "{} says it has {} persons to notify, but {} were found.",
+            data_group.name,
+            count,
+            persons_to_notify.len()
+        ),
+        _ => {}
+    }
+
+    // Deserialize the file from the given TLV data.
+    let result = types::EFDG16 {
+        persons_to_notify: persons_to_notify,
+    };
+    if print_data {
+        #[cfg(feature = "cli")]
+        result.fancy_print(data_group);
+    }
+    return Some(types::ParsedDataGroup::EFDG16(result));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ef_dg16_parsing() {
+        let data_group = &types::DATA_GROUPS[types::DataGroupEnum::EFDg16 as usize];
+        let parse = |data: Vec<u8>| match parser(&data, data_group, false) {
+            Some(types::ParsedDataGroup::EFDG16(ef_dg16_file)) => ef_dg16_file,
+            parsed_data => panic!("Expected EFDG16 but got {:x?}", parsed_data),
+        };
+        // A1 with all elements, A2 without a date or telephone
+        let first_person = [
+            &[0x5F, 0x50, 0x08][..],
+            b"20240101",
+            &[0x5F, 0x51, 0x09],
+            b"DOE<<JOHN",
+            &[0x5F, 0x52, 0x04],
+            b"1234",
+            &[0x5F, 0x53, 0x04],
+            b"HOME",
+        ]
+        .concat();
+        let second_person = [
+            &[0x5F, 0x51, 0x08][..],
+            b"DOE<<JAN",
+            &[0x5F, 0x53, 0x04],
+            b"WORK",
+        ]
+        .concat();
+        let templates = [
+            &[0xA1, first_person.len() as u8][..],
+            &first_person,
+            &[0xA2, second_person.len() as u8],
+            &second_person,
+        ]
+        .concat();
+        let build = |count: u8| {
+            let value = [&[0x02, 0x01, count][..], &templates].concat();
+            return [&[0x70, value.len() as u8][..], &value].concat();
+        };
+
+        let ef_dg16_file = parse(build(2));
+        assert_eq!(ef_dg16_file.persons_to_notify.len(), 2);
+        let first = &ef_dg16_file.persons_to_notify[0];
+        assert_eq!(first.date_recorded, Some("20240101".to_string()));
+        assert_eq!(first.name, Some("DOE<<JOHN".to_string()));
+        assert_eq!(first.telephone, Some("1234".to_string()));
+        assert_eq!(first.address, Some("HOME".to_string()));
+        let second = &ef_dg16_file.persons_to_notify[1];
+        assert_eq!(second.date_recorded, None);
+        assert_eq!(second.name, Some("DOE<<JAN".to_string()));
+        assert_eq!(second.telephone, None);
+        assert_eq!(second.address, Some("WORK".to_string()));
+
+        // A count that doesn't match the templates is only warned about
+        let ef_dg16_file = parse(build(3));
+        assert_eq!(ef_dg16_file.persons_to_notify.len(), 2);
+    }
+}
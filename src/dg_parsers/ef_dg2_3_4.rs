@@ -1,11 +1,158 @@
 use crate::dg_parsers::generic::dumper as generic_dumper;
 use crate::dg_parsers::helpers as dg_helpers;
+#[cfg(feature = "jpeg2000")]
+use crate::dg_parsers::jpeg2000;
+use crate::dg_parsers::wsq;
 use crate::helpers;
+#[cfg(feature = "preview")]
+use crate::preview;
 use crate::types;
 use iso7816_tlv::ber;
 use simplelog::{debug, info, warn};
 use std::{fs, io, path::Path};
 
+impl types::FaceDetails {
+    #[cfg(feature = "cli")]
+    pub fn fancy_print(&self) {
+        dg_helpers::print_option_string_element("Gender", &self.gender);
+        dg_helpers::print_option_string_element("Eye colour", &self.eye_colour);
+        dg_helpers::print_option_string_element("Hair colour", &self.hair_colour);
+        match self.properties.as_ref() {
+            Some(properties) if properties.is_empty() => {
+                dg_helpers::print_string_element("Properties", &"None".to_string())
+            }
+            Some(properties) => {
+                dg_helpers::print_string_element("Properties", &properties.join(", "))
+            }
+            None => {}
+        }
+        dg_helpers::print_option_string_element("Expression", &self.expression);
+        match self.pose_angle.as_ref() {
+            Some(pose_angle) => {
+                let format_angle = |angle: Option<i16>| match angle {
+                    Some(angle) => format!("{}°", angle),
+                    None => "?".to_string(),
+                };
+                dg_helpers::print_string_element(
+                    "Pose angle",
+                    &format!(
+                        "yaw {}, pitch {}, roll {}",
+                        format_angle(pose_angle.yaw),
+                        format_angle(pose_angle.pitch),
+                        format_angle(pose_angle.roll)
+                    ),
+                );
+            }
+            None => {}
+        }
+        for feature_point in self.feature_points.iter() {
+            dg_helpers::print_string_element(
+                &format!(
+                    "Feature point {}.{}",
+                    feature_point.major_code, feature_point.minor_code
+                ),
+                &format!(
+                    "({}, {}), type {}",
+                    feature_point.x, feature_point.y, feature_point.point_type
+                ),
+            );
+        }
+        dg_helpers::print_option_string_element("Face image type", &self.face_image_type);
+        dg_helpers::print_option_string_element("Colour space", &self.colour_space);
+        dg_helpers::print_option_string_element("Source type", &self.source_type);
+        match (self.image_width, self.image_height) {
+            (Some(width), Some(height)) => dg_helpers::print_string_element(
+                "Image dimensions",
+                &format!("{}x{}", width, height),
+            ),
+            _ => {}
+        }
+    }
+}
+
+impl types::FingerDetails {
+    #[cfg(feature = "cli")]
+    pub fn fancy_print(&self) {
+        dg_helpers::print_string_element("Finger position", &format!("{:?}", self.finger_position));
+        dg_helpers::print_string_element(
+            "View",
+            &format!("{} of {}", self.view_number, self.view_count),
+        );
+        let quality = match self.quality {
+            254 => "Not available".to_string(),
+            255 => "Unknown".to_string(),
+            quality => quality.to_string(),
+        };
+        dg_helpers::print_string_element("Quality", &quality);
+        dg_helpers::print_string_element("Impression type", &self.impression_type);
+        dg_helpers::print_string_element("Compression", &self.compression_algorithm);
+        dg_helpers::print_string_element(
+            "Image resolution",
+            &format!(
+                "{}x{} ({})",
+                self.horizontal_image_resolution, self.vertical_image_resolution, self.scale_units
+            ),
+        );
+        dg_helpers::print_string_element("Pixel depth", &format!("{} bits", self.pixel_depth));
+        dg_helpers::print_string_element(
+            "Image dimensions",
+            &format!("{}x{}", self.image_width, self.image_height),
+        );
+    }
+}
+
+impl types::IrisDetails {
+    #[cfg(feature = "cli")]
+    pub fn fancy_print(&self) {
+        dg_helpers::print_string_element("Eye", &format!("{:?}", self.eye_label));
+        dg_helpers::print_string_element(
+            "Image",
+            &format!(
+                "#{} ({} of this eye)",
+                self.image_sequence_number, self.image_count
+            ),
+        );
+        let quality = match self.quality {
+            254 => "Not available".to_string(),
+            255 => "Unknown".to_string(),
+            quality => quality.to_string(),
+        };
+        dg_helpers::print_string_element("Quality", &quality);
+        match self.rotation_angle {
+            Some(rotation_angle) => dg_helpers::print_string_element(
+                "Rotation angle",
+                &format!("{:.1}°", rotation_angle),
+            ),
+            None => {}
+        }
+        dg_helpers::print_string_element("Image type", &self.image_type);
+        dg_helpers::print_string_element("Image format", &self.image_format);
+        dg_helpers::print_string_element(
+            "Capture device ID",
+            &format!("{:04x}", self.capture_device_id),
+        );
+        dg_helpers::print_option_string_element(
+            "Device unique identifier",
+            &self.device_unique_identifier,
+        );
+        match self.iris_diameter {
+            Some(iris_diameter) => dg_helpers::print_string_element(
+                "Iris diameter",
+                &format!("{} pixels", iris_diameter),
+            ),
+            None => {}
+        }
+        dg_helpers::print_string_element(
+            "Intensity depth",
+            &format!("{} bits", self.intensity_depth),
+        );
+        dg_helpers::print_string_element(
+            "Image dimensions",
+            &format!("{}x{}", self.image_width, self.image_height),
+        );
+    }
+}
+
 impl types::EFDG2_3_4 {
     #[cfg(feature = "cli")]
     pub fn fancy_print(&self, data_group: &types::DataGroup) {
@@ -42,7 +189,19 @@ impl types::EFDG2_3_4 {
                 "Image format",
                 &biometric.image_format.get_extension(),
             );
+            match biometric.details.as_ref() {
+                Some(types::BiometricDetails::Face(face_details)) => face_details.fancy_print(),
+                Some(types::BiometricDetails::Finger(finger_details)) => {
+                    finger_details.fancy_print()
+                }
+                Some(types::BiometricDetails::Iris(iris_details)) => iris_details.fancy_print(),
+                None => {}
+            }
             dg_helpers::print_option_binary_element("Image Data", &Some(&biometric.data));
+            #[cfg(feature = "preview")]
+            if data_group.dg_num == 2 {
+                preview::print_image(&biometric.data, biometric.image_format);
+            }
         }
         info!("");
     }
@@ -106,7 +265,30 @@ pub fn dumper(
 
     // Dump all biometrics
     for (i, biometric) in ef_dg2_file.biometrics.iter().enumerate() {
-        let image_filename = format!("{}-pic{}", base_filename, i + 1);
+        // Fingers and irises are named by their position, so that they're easy to tell apart.
+        let image_filename = match biometric.details.as_ref() {
+            Some(types::BiometricDetails::Finger(finger_details)) => {
+                let finger_position: &str = finger_details.finger_position.into();
+                match finger_details.view_count {
+                    0 | 1 => format!("{}-{}", base_filename, finger_position),
+                    _ => format!(
+                        "{}-{}-view{}",
+                        base_filename, finger_position, finger_details.view_number
+                    ),
+                }
+            }
+            Some(types::BiometricDetails::Iris(iris_details)) => {
+                let eye_label: &str = iris_details.eye_label.into();
+                match iris_details.image_count {
+                    0 | 1 => format!("{}-{}", base_filename, eye_label),
+                    _ => format!(
+                        "{}-{}-{}",
+                        base_filename, eye_label, iris_details.image_sequence_number
+                    ),
+                }
+            }
+            _ => format!("{}-pic{}", base_filename, i + 1),
+        };
         let mut file_path = base_path.join(image_filename);
         file_path.set_extension(biometric.image_format.get_extension());
 
@@ -119,6 +301,31 @@ pub fn dumper(
             "<magenta>Saved image to {}</>",
             &file_path.to_string_lossy()
         );
+
+        #[cfg(feature = "jpeg2000")]
+        if biometric.image_format == types::BiometricImageFormat::Jpeg2000 {
+            jpeg2000::dump_as_png(&biometric.data, &file_path)?;
+        }
+
+        // Few viewers support WSQ, so also save fingerprints as PGM.
+        if biometric.image_format == types::BiometricImageFormat::Wsq {
+            let wsq_image = match wsq::decode(&biometric.data) {
+                Some(wsq_image) => wsq_image,
+                None => {
+                    warn!("Couldn't decode WSQ image, only saved the raw WSQ file.");
+                    continue;
+                }
+            };
+            file_path.set_extension("pgm");
+            let mut f = fs::File::create(&file_path)?;
+            io::Write::write_all(&mut f, &wsq_image.to_pgm())?;
+            f.sync_all()?;
+
+            info!(
+                "<magenta>Saved decoded image to {}</>",
+                &file_path.to_string_lossy()
+            );
+        }
     }
     return Ok(());
 }
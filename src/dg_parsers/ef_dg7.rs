@@ -1,5 +1,7 @@
 use crate::dg_parsers::generic::dumper as generic_dumper;
 use crate::dg_parsers::helpers as dg_helpers;
+#[cfg(feature = "jpeg2000")]
+use crate::dg_parsers::jpeg2000;
 use crate::helpers;
 use crate::types;
 use iso7816_tlv::ber;
@@ -81,7 +83,10 @@ pub fn dumper(
     for (i, picture_data) in ef_dg7_file.displayed_signatures.iter().enumerate() {
         let image_filename = format!("{}-pic{}", base_filename, i + 1);
         let mut file_path = base_path.join(image_filename);
-        file_path.set_extension("jpeg");
+        // ICAO 9303 allows both JPEG and JPEG 2000 here.
+        let image_format = types::BiometricImageFormat::from_magic(picture_data)
+            .unwrap_or(types::BiometricImageFormat::Jpeg);
+        file_path.set_extension(image_format.get_extension());
 
         // Create, write to and sync file.
         let mut f = fs::File::create(&file_path)?;
@@ -92,6 +97,11 @@ pub fn dumper(
             "<magenta>Saved image to {}</>",
             &file_path.to_string_lossy()
         );
+
+        #[cfg(feature = "jpeg2000")]
+        if image_format == types::BiometricImageFormat::Jpeg2000 {
+            jpeg2000::dump_as_png(picture_data, &file_path)?;
+        }
     }
     return Ok(());
 }
@@ -0,0 +1,118 @@
+use crate::dg_parsers::helpers as dg_helpers;
+use crate::helpers;
+use crate::icao9303;
+use crate::types;
+use simplelog::{debug, info, warn};
+
+impl types::EFDir {
+    #[cfg(feature = "cli")]
+    pub fn fancy_print(&self, data_group: &types::DataGroup) {
+        dg_helpers::print_section_intro(data_group);
+        for (i, application) in self.applications.iter().enumerate() {
+            info!(
+                "{:^pad_len$}",
+                format!("<b><u>Application #{}</>", i + 1),
+                // + 9 for b, u and /
+                pad_len = dg_helpers::SECTION_TITLE_PAD_TO_LEN + 9
+            );
+            dg_helpers::print_option_binary_element("AID", &Some(&application.aid));
+            dg_helpers::print_option_string_element("Label", &application.label);
+            let application_name = match icao9303::get_application_name(&application.aid) {
+                Some(name) => name.to_string(),
+                None => "Unknown".to_string(),
+            };
+            dg_helpers::print_string_element("Application", &application_name);
+        }
+        info!("");
+    }
+
+    /// Returns whether the directory lists the application with the given AID.
+    pub fn has_application(&self, aid: &[u8]) -> bool {
+        return self
+            .applications
+            .iter()
+            .any(|application| application.aid == aid);
+    }
+}
+
+pub fn parser(
+    data: &Vec<u8>,
+    data_group: &types::DataGroup,
+    print_data: bool,
+) -> Option<types::ParsedDataGroup> {
+    // EF.DIR is a list of application templates (61) rather than a single TLV.
+    let tlvs = match dg_helpers::parse_tlvs(data) {
+        Some(tlvs) => tlvs,
+        None => {
+            warn!(
+                "Couldn't parse {} as TLVs, skipping parsing.",
+                data_group.name
+            );
+            return None;
+        }
+    };
+    debug!("tlvs: {:02x?}", tlvs);
+
+    let mut applications: Vec<types::Application> = vec![];
+    for application_template in helpers::get_tlvs_by_tag(&tlvs, 0x61) {
+        let template_value = match dg_helpers::tlv_get_constructed_value(application_template) {
+            Some(template_value) => template_value,
+            None => {
+                warn!("Found a primitive application template, skipping it.");
+                continue;
+            }
+        };
+        let template_tlvs = helpers::sort_tlvs_by_tag(&template_value);
+        let aid = match dg_helpers::tlv_get_bytes(&template_tlvs, &0x4F) {
+            Some(aid) => aid,
+            None => {
+                warn!("Found an application template without an AID, skipping it.");
+                continue;
+            }
+        };
+        applications.push(types::Application {
+            aid: aid,
+            label: dg_helpers::tlv_get_bytes(&template_tlvs, &0x50)
+                .map(|label| String::from_utf8_lossy(&label).to_string()),
+        });
+    }
+
+    // Deserialize the file from the given TLV data.
+    let result = types::EFDir {
+        applications: applications,
+    };
+    if print_data {
+        #[cfg(feature = "cli")]
+        result.fancy_print(data_group);
+    }
+    return Some(types::ParsedDataGroup::EFDir(result));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ef_dir_parsing() {
+        // LDS1 with a label, and Travel Records without one
+        let data: Vec<u8> = [
+            &[0x61, 0x0F, 0x4F, 0x07][..],
+            &icao9303::AID_MRTD_LDS1,
+            &[0x50, 0x04],
+            b"ICAO",
+            &[0x61, 0x09, 0x4F, 0x07],
+            &icao9303::AID_LDS2_TRAVEL_RECORDS,
+        ]
+        .concat();
+        let data_group = &types::DATA_GROUPS[types::DataGroupEnum::EFDir as usize];
+        let ef_dir_file = match parser(&data, data_group, false) {
+            Some(types::ParsedDataGroup::EFDir(ef_dir_file)) => ef_dir_file,
+            parsed_data => panic!("Expected EFDir but got {:x?}", parsed_data),
+        };
+        assert_eq!(ef_dir_file.applications.len(), 2);
+        assert_eq!(ef_dir_file.applications[0].label, Some("ICAO".to_string()));
+        assert_eq!(ef_dir_file.applications[1].label, None);
+        assert!(ef_dir_file.has_application(&icao9303::AID_LDS2_TRAVEL_RECORDS));
+        assert!(!ef_dir_file.has_application(&icao9303::AID_EID));
+    }
+}
@@ -0,0 +1,203 @@
+use crate::dg_parsers::helpers as dg_helpers;
+use crate::helpers;
+use crate::icao9303;
+use crate::types;
+use iso7816_tlv::ber;
+use simplelog::{debug, info, warn};
+
+// RFC 5652 and ICAO 9303 p10, 4.6.2.3
+const OID_SIGNED_DATA: &str = "1.2.840.113549.1.7.2";
+const OID_LDS_SECURITY_OBJECT: &str = "2.23.136.1.1.1";
+
+impl types::EFSod {
+    #[cfg(feature = "cli")]
+    pub fn fancy_print(&self, data_group: &types::DataGroup) {
+        dg_helpers::print_section_intro(data_group);
+        let hash_algorithm_name = match icao9303::get_hash_algorithm_name(&self.hash_algorithm) {
+            Some(name) => name.to_string(),
+            None => format!("Unknown ({})", self.hash_algorithm),
+        };
+        dg_helpers::print_string_element("Hash algorithm", &hash_algorithm_name);
+        for data_group_hash in self.data_group_hashes.iter() {
+            dg_helpers::print_option_binary_element(
+                &format!("DG{} hash", data_group_hash.dg_num),
+                &Some(&data_group_hash.hash),
+            );
+        }
+        info!("");
+    }
+
+    /// Checks a data group against its hash.
+    ///
+    /// Returns None if there's no hash for the data group or the hash algorithm isn't supported.
+    pub fn verify_data_group(&self, dg_num: u8, data: &[u8]) -> Option<bool> {
+        let data_group_hash = self
+            .data_group_hashes
+            .iter()
+            .find(|data_group_hash| data_group_hash.dg_num == dg_num)?;
+        let hash = icao9303::hash_with_oid(&self.hash_algorithm, data)?;
+        return Some(hash == data_group_hash.hash);
+    }
+}
+
+/// Returns the value of the first constructed TLV with the given tag.
+fn get_constructed_child(tlvs: &Vec<ber::Tlv>, tag: u16) -> Option<Vec<ber::Tlv>> {
+    return dg_helpers::tlv_get_constructed_value(helpers::get_tlv_by_tag(tlvs, tag)?);
+}
+
+/// Returns the decoded value of the first OID.
+fn get_oid_child(tlvs: &Vec<ber::Tlv>) -> Option<String> {
+    return dg_helpers::decode_oid(&helpers::get_tlv_value_bytes(helpers::get_tlv_by_tag(
+        tlvs, 0x06,
+    )?));
+}
+
+/// Digs the LDS Security Object out of the CMS SignedData structure (RFC 5652).
+fn get_lds_security_object(base_tlv_value: &Vec<ber::Tlv>) -> Option<Vec<ber::Tlv>> {
+    // ContentInfo -> [0] -> SignedData
+    let content_info = get_constructed_child(base_tlv_value, 0x30)?;
+    if get_oid_child(&content_info)? != OID_SIGNED_DATA {
+        warn!("EF.SOD doesn't hold a CMS SignedData structure.");
+        return None;
+    }
+    let signed_data = get_constructed_child(&get_constructed_child(&content_info, 0xA0)?, 0x30)?;
+
+    // EncapsulatedContentInfo -> [0] -> OCTET STRING
+    let encap_content_info = get_constructed_child(&signed_data, 0x30)?;
+    match get_oid_child(&encap_content_info) {
+        Some(oid) if oid == OID_LDS_SECURITY_OBJECT => {}
+        oid => warn!(
+            "EF.SOD content type is {:?} rather than the LDS Security Object, trying anyway.",
+            oid
+        ),
+    }
+    let econtent = get_constructed_child(&encap_content_info, 0xA0)?;
+    let lds_security_object_data =
+        helpers::get_tlv_value_bytes(helpers::get_tlv_by_tag(&econtent, 0x04)?);
+    let lds_security_object = ber::Tlv::parse(&lds_security_object_data).0.ok()?;
+    return dg_helpers::tlv_get_constructed_value(&lds_security_object);
+}
+
+pub fn parser(
+    data: &Vec<u8>,
+    data_group: &types::DataGroup,
+    print_data: bool,
+) -> Option<types::ParsedDataGroup> {
+    // Parse the base TLV
+    let base_tlv = ber::Tlv::parse(data).0.ok()?;
+    let base_tlv_tag = helpers::get_tlv_tag(&base_tlv);
+    if base_tlv_tag != data_group.tag.into() {
+        warn!(
+            "Found {}'s TLV tag as 0x{} (expected 0x{}), skipping parsing.",
+            data_group.name, base_tlv_tag, data_group.tag
+        );
+        return None;
+    };
+    let base_tlv_value = dg_helpers::tlv_get_constructed_value(&base_tlv)?;
+
+    let lds_security_object = match get_lds_security_object(&base_tlv_value) {
+        Some(lds_security_object) => lds_security_object,
+        None => {
+            warn!(
+                "Couldn't find the LDS Security Object in {}, skipping parsing.",
+                data_group.name
+            );
+            return None;
+        }
+    };
+    debug!("lds_security_object: {:02x?}", lds_security_object);
+
+    // LDSSecurityObject: version, hashAlgorithm, dataGroupHashValues
+    let sequences = helpers::get_tlvs_by_tag(&lds_security_object, 0x30);
+    if sequences.len() < 2 {
+        warn!(
+            "{}'s LDS Security Object is missing fields.",
+            data_group.name
+        );
+        return None;
+    }
+    let hash_algorithm = get_oid_child(&dg_helpers::tlv_get_constructed_value(sequences[0])?)?;
+
+    let mut data_group_hashes: Vec<types::DataGroupHash> = vec![];
+    for data_group_hash in dg_helpers::tlv_get_constructed_value(sequences[1])?.iter() {
+        let data_group_hash_value = dg_helpers::tlv_get_constructed_value(data_group_hash)?;
+        let dg_num =
+            dg_helpers::tlv_get_integer(helpers::get_tlv_by_tag(&data_group_hash_value, 0x02)?)?;
+        let hash =
+            helpers::get_tlv_value_bytes(helpers::get_tlv_by_tag(&data_group_hash_value, 0x04)?);
+        data_group_hashes.push(types::DataGroupHash {
+            dg_num: dg_num as u8,
+            hash: hash,
+        });
+    }
+
+    // Deserialize the file from the given TLV data.
+    let result = types::EFSod {
+        hash_algorithm: hash_algorithm,
+        data_group_hashes: data_group_hashes,
+    };
+    if print_data {
+        #[cfg(feature = "cli")]
+        result.fancy_print(data_group);
+    }
+    return Some(types::ParsedDataGroup::EFSod(result));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tlv(tag: u8, value: &[u8]) -> Vec<u8> {
+        return [&[tag, value.len() as u8][..], value].concat();
+    }
+
+    #[test]
+    fn ef_sod_parsing() {
+        let dg1_data = b"abc";
+        let dg1_hash = icao9303::hash_with_oid(icao9303::OID_SHA256, dg1_data).unwrap();
+        // SHA-256 and LDS Security Object OIDs
+        let sha256_oid = [0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x02, 0x01];
+        let lds_security_object_oid = [0x67, 0x81, 0x08, 0x01, 0x01, 0x01];
+        let signed_data_oid = [0x2A, 0x86, 0x48, 0x86, 0xF7, 0x0D, 0x01, 0x07, 0x02];
+
+        let lds_security_object = tlv(
+            0x30,
+            &[
+                tlv(0x02, &[0x00]),
+                tlv(0x30, &tlv(0x06, &sha256_oid)),
+                tlv(
+                    0x30,
+                    &tlv(0x30, &[tlv(0x02, &[0x01]), tlv(0x04, &dg1_hash)].concat()),
+                ),
+            ]
+            .concat(),
+        );
+        let encap_content_info = tlv(
+            0x30,
+            &[
+                tlv(0x06, &lds_security_object_oid),
+                tlv(0xA0, &tlv(0x04, &lds_security_object)),
+            ]
+            .concat(),
+        );
+        let signed_data = tlv(
+            0x30,
+            &[tlv(0x02, &[0x03]), tlv(0x31, &[]), encap_content_info].concat(),
+        );
+        let content_info = tlv(
+            0x30,
+            &[tlv(0x06, &signed_data_oid), tlv(0xA0, &signed_data)].concat(),
+        );
+        let data = tlv(0x77, &content_info);
+
+        let data_group = &types::DATA_GROUPS[types::DataGroupEnum::EFSod as usize];
+        let ef_sod_file = match parser(&data, data_group, false) {
+            Some(types::ParsedDataGroup::EFSod(ef_sod_file)) => ef_sod_file,
+            _ => panic!("Couldn't parse EF.SOD"),
+        };
+        assert_eq!(ef_sod_file.hash_algorithm, icao9303::OID_SHA256);
+        assert_eq!(ef_sod_file.verify_data_group(1, dg1_data), Some(true));
+        assert_eq!(ef_sod_file.verify_data_group(1, b"abd"), Some(false));
+        assert_eq!(ef_sod_file.verify_data_group(2, dg1_data), None);
+    }
+}
@@ -17,10 +17,14 @@ pub fn parser(
             // + 6 for bold
             pad_len = dg_helpers::SECTION_TITLE_PAD_TO_LEN + 6
         );
-        dg_helpers::print_option_binary_element(
-            &format!("Raw data ({}b)", data.len()),
-            &Some(data),
-        );
+        // Most files are BER-TLV encoded, so show their structure if we can.
+        match dg_helpers::parse_tlvs(data) {
+            Some(tlvs) => dg_helpers::print_tlv_tree(&tlvs, 0),
+            None => dg_helpers::print_option_binary_element(
+                &format!("Raw data ({}b)", data.len()),
+                &Some(data),
+            ),
+        }
     }
     return None;
 }
@@ -2,10 +2,12 @@ use iso7816_tlv::ber;
 use simplelog::{debug, info, warn};
 use std::{cmp::max, collections::HashMap};
 
+use crate::dg_parsers::{iso19794_4, iso19794_5, iso19794_6, iso39794_5};
 use crate::{helpers, types};
 
 pub(crate) const SECTION_TITLE_PAD_TO_LEN: usize = 56;
 const PRINT_TITLE_PAD_TO_LEN: usize = 25;
+const TLV_TREE_MAX_HEX_LEN: usize = 32;
 
 pub(crate) fn tlv_get_string_value(tlvs: &HashMap<u16, &ber::Tlv>, tag: &u16) -> Option<String> {
     match tlvs.get(tag) {
@@ -31,6 +33,192 @@ pub(crate) fn tlv_get_byte(tlvs: &HashMap<u16, &ber::Tlv>, tag: &u16) -> Option<
     }
 }
 
+/// Looks up the name of a code in a table, falling back to the number itself.
+pub(crate) fn code_name(names: &[&str], code: i64) -> String {
+    match usize::try_from(code)
+        .ok()
+        .and_then(|index| names.get(index))
+    {
+        Some(name) => name.to_string(),
+        None => format!("Code {}", code),
+    }
+}
+
+/// Reads a byte at the given offset, or None if it's out of bounds.
+pub(crate) fn read_u8(data: &[u8], offset: usize) -> Option<u8> {
+    return data.get(offset).copied();
+}
+
+/// Reads a big-endian u16 at the given offset, or None if it's out of bounds.
+pub(crate) fn read_u16(data: &[u8], offset: usize) -> Option<u16> {
+    return Some(u16::from_be_bytes(
+        data.get(offset..offset + 2)?.try_into().ok()?,
+    ));
+}
+
+/// Reads a big-endian 3 byte integer at the given offset, or None if it's out of bounds.
+pub(crate) fn read_u24(data: &[u8], offset: usize) -> Option<u32> {
+    let bytes = data.get(offset..offset + 3)?;
+    return Some(u32::from_be_bytes([0, bytes[0], bytes[1], bytes[2]]));
+}
+
+/// Reads a big-endian u32 at the given offset, or None if it's out of bounds.
+pub(crate) fn read_u32(data: &[u8], offset: usize) -> Option<u32> {
+    return Some(u32::from_be_bytes(
+        data.get(offset..offset + 4)?.try_into().ok()?,
+    ));
+}
+
+/// Returns the TLVs inside a constructed TLV, or None if the TLV is primitive.
+pub(crate) fn tlv_get_constructed_value(tlv: &ber::Tlv) -> Option<Vec<ber::Tlv>> {
+    match tlv.value() {
+        ber::Value::Constructed(tlvs) => Some(tlvs.clone()),
+        ber::Value::Primitive(_) => None,
+    }
+}
+
+/// Decodes the value of a primitive TLV as a signed big-endian (ASN.1 DER) integer.
+pub(crate) fn tlv_get_integer(tlv: &ber::Tlv) -> Option<i64> {
+    match tlv.value() {
+        ber::Value::Primitive(data) => return decode_integer(data),
+        ber::Value::Constructed(_) => return None,
+    };
+}
+
+/// Decodes a signed big-endian (ASN.1 DER) integer.
+pub(crate) fn decode_integer(value_bytes: &[u8]) -> Option<i64> {
+    if value_bytes.is_empty() || value_bytes.len() > 8 {
+        return None;
+    }
+    // Sign extend based on the first bit of the first byte.
+    let mut padded_bytes = if value_bytes[0] & 0x80 != 0 {
+        [0xFFu8; 8]
+    } else {
+        [0x00u8; 8]
+    };
+    padded_bytes[8 - value_bytes.len()..].copy_from_slice(value_bytes);
+    return Some(i64::from_be_bytes(padded_bytes));
+}
+
+/// Decodes an ASN.1 object identifier into its dotted form, like "2.23.136.1.1.1".
+pub(crate) fn decode_oid(data: &[u8]) -> Option<String> {
+    // Each arc is base 128, with the top bit set on all but the last byte.
+    let mut arcs: Vec<u64> = vec![];
+    let mut arc: u64 = 0;
+    for byte in data.iter() {
+        arc = arc.checked_mul(128)? | (byte & 0x7F) as u64;
+        if byte & 0x80 == 0 {
+            arcs.push(arc);
+            arc = 0;
+        }
+    }
+    if arcs.is_empty() || data.last()? & 0x80 != 0 {
+        return None;
+    }
+    // The first arc encodes the first two.
+    let (first_arc, second_arc) = match arcs[0] {
+        0..=39 => (0, arcs[0]),
+        40..=79 => (1, arcs[0] - 40),
+        _ => (2, arcs[0] - 80),
+    };
+    let mut oid = format!("{}.{}", first_arc, second_arc);
+    for arc in arcs[1..].iter() {
+        oid.push_str(&format!(".{}", arc));
+    }
+    return Some(oid);
+}
+
+/// Parses data made up of consecutive BER-TLVs, None if any of it isn't a valid TLV.
+pub(crate) fn parse_tlvs(data: &[u8]) -> Option<Vec<ber::Tlv>> {
+    let mut tlvs: Vec<ber::Tlv> = vec![];
+    let mut remaining_data = data;
+    while !remaining_data.is_empty() {
+        let (tlv, rest) = ber::Tlv::parse(remaining_data);
+        tlvs.push(tlv.ok()?);
+        remaining_data = rest;
+    }
+    if tlvs.is_empty() {
+        return None;
+    }
+    return Some(tlvs);
+}
+
+/// Describes the value of a primitive TLV, guessing how to read it from its tag and contents.
+pub(crate) fn describe_tlv_value(tag: &ber::Tag, data: &[u8]) -> String {
+    if data.is_empty() {
+        return "(empty)".to_string();
+    }
+    // Universal types that don't read well as strings or hex
+    match tag.to_bytes() {
+        [0x01] if data.len() == 1 => return format!("BOOLEAN {}", data[0] != 0),
+        [0x02] => match decode_integer(data) {
+            Some(integer) => return format!("INTEGER {}", integer),
+            None => {}
+        },
+        [0x06] => match decode_oid(data) {
+            Some(oid) => return format!("OID {}", oid),
+            None => {}
+        },
+        _ => {}
+    }
+    match std::str::from_utf8(data) {
+        Ok(text) if !text.chars().any(|character| character.is_control()) => {
+            return format!("\"{}\"", text);
+        }
+        _ => {}
+    }
+    // Hex, cut short for long values like certificates or images
+    if data.len() > TLV_TREE_MAX_HEX_LEN {
+        return format!(
+            "{:02x?}... (+{}b)",
+            &data[..TLV_TREE_MAX_HEX_LEN],
+            data.len() - TLV_TREE_MAX_HEX_LEN
+        );
+    }
+    return format!("{:02x?}", data);
+}
+
+/// Parses a list of names in A0 templates, like DG11's other names or DG12's other persons.
+///
+/// Each A0 template holds the amount of names (02), followed by the names themselves.
+/// Some issuers put every name in its own A0 template instead, each with the total amount.
+pub(crate) fn parse_name_list(tlvs: &Vec<ber::Tlv>, name_tag: u16) -> Option<Vec<String>> {
+    let templates = helpers::get_tlvs_by_tag(tlvs, 0xA0);
+    if templates.is_empty() {
+        return None;
+    }
+
+    let mut name_count: Option<usize> = None;
+    let mut names: Vec<String> = vec![];
+    for template in templates {
+        let template_tlvs = match tlv_get_constructed_value(template) {
+            Some(template_tlvs) => template_tlvs,
+            None => {
+                warn!("Found a primitive name list template, skipping it.");
+                continue;
+            }
+        };
+        match helpers::get_tlv_by_tag(&template_tlvs, 0x02).and_then(tlv_get_integer) {
+            Some(count) if name_count.is_none() => name_count = usize::try_from(count).ok(),
+            _ => {}
+        }
+        for name_tlv in helpers::get_tlvs_by_tag(&template_tlvs, name_tag) {
+            let value_bytes = helpers::get_tlv_value_bytes(name_tlv);
+            names.push(String::from_utf8_lossy(&value_bytes).to_string());
+        }
+    }
+
+    match name_count {
+        Some(name_count) if name_count != names.len() => warn!(
+            "Name list says it has {} names, but {} were found.",
+            name_count,
+            names.len()
+        ),
+        _ => {}
+    }
+    return Some(names);
+}
+
 pub(crate) fn parse_biometric_info_template_group_template(
     biometric_info_template_group_template_tlv: &ber::Tlv,
 ) -> Vec<types::Biometric> {
@@ -50,54 +238,66 @@ pub(crate) fn parse_biometric_info_template_group_template(
     for biometric_info_template in biometric_info_template_tlvs {
         let tlv_value = helpers::get_tlv_constructed_value(&biometric_info_template);
         let biometric_info_tlvs = helpers::sort_tlvs_by_tag(&tlv_value);
-        // Here should be 0xA1 (header template), plus data: 0x5F2E (ISO/IEC 19794-5) or 0x7F2E (ISO/IEC 39794)
-        let image_data: Vec<u8>;
-        let mut image_format: types::BiometricImageFormat = types::BiometricImageFormat::Reserved;
+        // Here should be 0xA1 (header template), plus data: 0x5F2E (ISO/IEC 19794-4/5/6) or 0x7F2E (ISO/IEC 39794)
+        let mut representations: Vec<(
+            Vec<u8>,
+            types::BiometricImageFormat,
+            Option<types::BiometricDetails>,
+        )> = vec![];
         if biometric_info_tlvs.contains_key(&0x5F2E) {
             let iso_19794_data =
                 helpers::get_tlv_value_bytes(biometric_info_tlvs.get(&0x5F2E).unwrap());
-            // quick lazy implementation of ISO/IEC 19794
-            // Only allow 2005 variant (this is what ICAO 9303 requires for first biometric)
-            if iso_19794_data[4..8] != [0x30, 0x31, 0x30, 00] {
-                warn!(
-                    "Biometric has unsupported version, skipping: {:02x?}",
-                    &iso_19794_data[4..8]
-                );
-                continue;
-            }
-
-            let number_of_representations =
-                u16::from_be_bytes(iso_19794_data[12..14].try_into().unwrap());
-            if number_of_representations != 1 {
-                warn!("Expected one representation of biometric, but found {}. We can only dump the first one.", number_of_representations)
-            }
-            let rep_1_start = 14; // size of general header on ISO/IEC 19794-1:2006
-            let rep_1_length = u32::from_be_bytes(
-                iso_19794_data[rep_1_start..rep_1_start + 4]
-                    .try_into()
-                    .unwrap(),
-            );
-            let rep_1_feature_point_count = u16::from_be_bytes(
-                iso_19794_data[rep_1_start + 4..rep_1_start + 6]
-                    .try_into()
-                    .unwrap(),
-            );
-            let rep_1_header_length: u16 = 20 + (8 * rep_1_feature_point_count) + 12;
-            let rep_1_data = &iso_19794_data
-                [rep_1_start + rep_1_header_length as usize..rep_1_start + rep_1_length as usize];
-            let rep_1_image_format = iso_19794_data[36 + (8 * rep_1_feature_point_count as usize)];
-
-            match types::BiometricImageFormat::from_repr(rep_1_image_format as usize) {
-                Some(format) => {
-                    image_format = format;
+            // The format identifier tells us which part of ISO/IEC 19794 the record is from.
+            match iso_19794_data.get(0..4) {
+                Some(b"FIR\0") => {
+                    for (image_data, image_format, finger_details) in
+                        iso19794_4::parse_finger_image_record(&iso_19794_data)
+                    {
+                        representations.push((
+                            image_data,
+                            image_format,
+                            Some(types::BiometricDetails::Finger(finger_details)),
+                        ));
+                    }
+                }
+                Some(b"IIR\0") => {
+                    for (image_data, image_format, iris_details) in
+                        iso19794_6::parse_iris_image_record(&iso_19794_data)
+                    {
+                        representations.push((
+                            image_data,
+                            image_format,
+                            Some(types::BiometricDetails::Iris(iris_details)),
+                        ));
+                    }
+                }
+                _ => {
+                    for (image_data, image_format, face_details) in
+                        iso19794_5::parse_facial_record(&iso_19794_data)
+                    {
+                        representations.push((
+                            image_data,
+                            image_format,
+                            Some(types::BiometricDetails::Face(face_details)),
+                        ));
+                    }
                 }
-                None => {}
             }
-            // 36 + (8 * rep_1_feature_point_count)
-            image_data = rep_1_data.to_vec();
         } else if biometric_info_tlvs.contains_key(&0x7F2E) {
-            // ICAO 9303 requires ISO/IEC 19794 for first biometric so this is low-priority
-            todo!();
+            let biometric_data_block_tlv = biometric_info_tlvs.get(&0x7F2E).unwrap();
+            let biometric_data_block = match tlv_get_constructed_value(biometric_data_block_tlv) {
+                Some(tlvs) => tlvs.iter().flat_map(|tlv| tlv.to_vec()).collect(),
+                None => helpers::get_tlv_value_bytes(biometric_data_block_tlv),
+            };
+            for (image_data, image_format, face_details) in
+                iso39794_5::parse_face_image_data_block(&biometric_data_block)
+            {
+                representations.push((
+                    image_data,
+                    image_format,
+                    Some(types::BiometricDetails::Face(face_details)),
+                ));
+            }
         } else {
             warn!("Biometric info template does not contain data.");
             continue;
@@ -107,19 +307,23 @@ pub(crate) fn parse_biometric_info_template_group_template(
             helpers::get_tlv_constructed_value(biometric_info_tlvs.get(&0xA1).unwrap());
         let biometric_header_tlvs = helpers::sort_tlvs_by_tag(&biometric_header_template);
 
-        let biometric = types::Biometric {
-            header_version: tlv_get_bytes(&biometric_header_tlvs, &0x80),
-            biometric_type: tlv_get_bytes(&biometric_header_tlvs, &0x81),
-            biometric_sub_type: tlv_get_byte(&biometric_header_tlvs, &0x82),
-            creation_timestamp: tlv_get_bytes(&biometric_header_tlvs, &0x83),
-            validity_period_from_through: tlv_get_bytes(&biometric_header_tlvs, &0x85),
-            creator_of_biometric_data: tlv_get_bytes(&biometric_header_tlvs, &0x86),
-            format_owner: tlv_get_bytes(&biometric_header_tlvs, &0x87).unwrap(),
-            format_type: tlv_get_bytes(&biometric_header_tlvs, &0x88).unwrap(),
-            data: image_data.clone(),
-            image_format: image_format,
-        };
-        biometrics.push(biometric);
+        // Each representation becomes its own biometric, sharing the header template.
+        for (image_data, image_format, details) in representations {
+            let biometric = types::Biometric {
+                header_version: tlv_get_bytes(&biometric_header_tlvs, &0x80),
+                biometric_type: tlv_get_bytes(&biometric_header_tlvs, &0x81),
+                biometric_sub_type: tlv_get_byte(&biometric_header_tlvs, &0x82),
+                creation_timestamp: tlv_get_bytes(&biometric_header_tlvs, &0x83),
+                validity_period_from_through: tlv_get_bytes(&biometric_header_tlvs, &0x85),
+                creator_of_biometric_data: tlv_get_bytes(&biometric_header_tlvs, &0x86),
+                format_owner: tlv_get_bytes(&biometric_header_tlvs, &0x87).unwrap(),
+                format_type: tlv_get_bytes(&biometric_header_tlvs, &0x88).unwrap(),
+                data: image_data,
+                image_format: image_format,
+                details: details,
+            };
+            biometrics.push(biometric);
+        }
     }
     return biometrics;
 }
@@ -386,6 +590,20 @@ pub(crate) fn print_string_element_as_mrz_date(title: &str, value: &String) {
     info!("{} <yellow>{}</>", pad_with_ellipses(title), date_str);
 }
 
+#[cfg(feature = "cli")]
+pub(crate) fn print_option_name_list_element(title: &str, value: &Option<Vec<String>>) {
+    let names = match value {
+        Some(names) => names,
+        None => return,
+    };
+    if names.is_empty() {
+        print_string_element(title, &"None".to_string());
+    }
+    for (i, name) in names.iter().enumerate() {
+        print_string_element_as_name(&format!("{} #{}", title, i + 1), name);
+    }
+}
+
 #[cfg(feature = "cli")]
 pub(crate) fn print_option_string_element_as_dg_date(title: &str, value: &Option<String>) {
     if value.is_none() {
@@ -422,6 +640,41 @@ where
     }
 }
 
+/// Prints the structure of BER-TLVs, one line per TLV, indenting the contents of constructed ones.
+#[cfg(feature = "cli")]
+pub(crate) fn print_tlv_tree(tlvs: &Vec<ber::Tlv>, depth: usize) {
+    for tlv in tlvs.iter() {
+        let indent = "  ".repeat(depth);
+        let tag: String = tlv
+            .tag()
+            .to_bytes()
+            .iter()
+            .map(|byte| format!("{:02X}", byte))
+            .collect();
+        match tlv.value() {
+            ber::Value::Constructed(child_tlvs) => {
+                let value_len: usize = child_tlvs.iter().map(|child| child.to_vec().len()).sum();
+                info!(
+                    "{}<b>{}</> ({:?}, constructed, {}b)",
+                    indent,
+                    tag,
+                    tlv.tag().class(),
+                    value_len
+                );
+                print_tlv_tree(child_tlvs, depth + 1);
+            }
+            ber::Value::Primitive(data) => info!(
+                "{}<b>{}</> ({:?}, {}b) <yellow>{}</>",
+                indent,
+                tag,
+                tlv.tag().class(),
+                data.len(),
+                describe_tlv_value(tlv.tag(), data)
+            ),
+        }
+    }
+}
+
 #[cfg(feature = "cli")]
 pub(crate) fn print_option_debug_element<T>(title: &str, value: &Option<T>)
 where
@@ -437,3 +690,57 @@ where
         value.clone().unwrap()
     );
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn name_list_parsing() {
+        // One A0 template with two names, as in ICAO 9303 part 10
+        let data: Vec<u8> = vec![
+            0xA0, 0x0F, 0x02, 0x01, 0x02, 0x5F, 0x0F, 0x03, b'A', b'<', b'B', 0x5F, 0x0F, 0x03,
+            b'C', b'<', b'D',
+        ];
+        let tlvs = vec![ber::Tlv::from_bytes(&data).unwrap()];
+        assert_eq!(
+            parse_name_list(&tlvs, 0x5F0F),
+            Some(vec!["A<B".to_string(), "C<D".to_string()])
+        );
+
+        // The same names, split across two A0 templates
+        let data: Vec<u8> = vec![
+            0xA0, 0x09, 0x02, 0x01, 0x02, 0x5F, 0x1A, 0x03, b'A', b'<', b'B', 0xA0, 0x09, 0x02,
+            0x01, 0x02, 0x5F, 0x1A, 0x03, b'C', b'<', b'D',
+        ];
+        let (first_tlv, rest) = ber::Tlv::parse(&data);
+        let tlvs = vec![first_tlv.unwrap(), ber::Tlv::from_bytes(rest).unwrap()];
+        assert_eq!(
+            parse_name_list(&tlvs, 0x5F1A),
+            Some(vec!["A<B".to_string(), "C<D".to_string()])
+        );
+        assert_eq!(parse_name_list(&vec![], 0x5F1A), None);
+    }
+
+    #[test]
+    fn tlv_value_description() {
+        let tag = |tag_number: u16| ber::Tag::try_from(tag_number).unwrap();
+        // id-icao-mrtd-security-ldsSecurityObject
+        assert_eq!(
+            describe_tlv_value(&tag(0x06), &[0x67, 0x81, 0x08, 0x01, 0x01, 0x01]),
+            "OID 2.23.136.1.1.1"
+        );
+        assert_eq!(
+            describe_tlv_value(&tag(0x02), &[0xFF, 0x38]),
+            "INTEGER -200"
+        );
+        assert_eq!(describe_tlv_value(&tag(0x5F1F), b"P<UTO"), "\"P<UTO\"");
+        assert_eq!(describe_tlv_value(&tag(0x5F1F), &[0x00, 0x9F]), "[00, 9f]");
+        assert_eq!(
+            describe_tlv_value(&tag(0x5F2E), &[0x00; 40]),
+            format!("{:02x?}... (+8b)", [0x00; 32])
+        );
+        assert!(parse_tlvs(&[0x01, 0x01, 0xFF, 0x02, 0x01, 0x05]).is_some());
+        assert!(parse_tlvs(&[0x01, 0x01, 0xFF, 0x02, 0x05]).is_none());
+    }
+}
@@ -0,0 +1,202 @@
+//! ISO/IEC 19794-4:2005 finger image records, as used for DG3.
+use crate::dg_parsers::helpers as dg_helpers;
+use crate::types;
+use simplelog::{debug, warn};
+
+// ISO/IEC 19794-4:2005, 8.2
+const FORMAT_IDENTIFIER: [u8; 4] = *b"FIR\0";
+const VERSION_NUMBER: [u8; 4] = *b"010\0";
+const GENERAL_HEADER_LEN: usize = 32;
+// ISO/IEC 19794-4:2005, 8.3
+const FINGER_HEADER_LEN: usize = 14;
+
+const SCALE_UNIT_NAMES: [&str; 3] = ["Unknown", "Pixels per inch", "Pixels per centimeter"];
+const COMPRESSION_ALGORITHM_NAMES: [&str; 6] = [
+    "Uncompressed (no bit packing)",
+    "Uncompressed (bit packed)",
+    "WSQ",
+    "JPEG",
+    "JPEG 2000",
+    "PNG",
+];
+const IMPRESSION_TYPE_NAMES: [&str; 9] = [
+    "Live-scan plain",
+    "Live-scan rolled",
+    "Non-live-scan plain",
+    "Non-live-scan rolled",
+    "Latent impression",
+    "Latent tracing",
+    "Latent photo",
+    "Latent lift",
+    "Swipe",
+];
+
+/// Parses the contents of a 5F2E biometric data block holding an ISO/IEC 19794-4 finger image record.
+///
+/// Returns (image data, image format, finger details) for each finger image in the record.
+pub(crate) fn parse_finger_image_record(
+    data: &[u8],
+) -> Vec<(Vec<u8>, types::BiometricImageFormat, types::FingerDetails)> {
+    let mut representations: Vec<(Vec<u8>, types::BiometricImageFormat, types::FingerDetails)> =
+        vec![];
+
+    // General record header
+    if data.get(0..4) != Some(&FORMAT_IDENTIFIER) {
+        warn!(
+            "Biometric isn't an ISO/IEC 19794-4 finger image record, skipping: {:02x?}",
+            data.get(0..4)
+        );
+        return representations;
+    }
+    if data.get(4..8) != Some(&VERSION_NUMBER) {
+        warn!(
+            "Biometric has unsupported version, skipping: {:02x?}",
+            data.get(4..8)
+        );
+        return representations;
+    }
+    if data.len() < GENERAL_HEADER_LEN {
+        warn!("Finger image record is shorter than its general header, skipping.");
+        return representations;
+    }
+    // Record length is 6 bytes long.
+    let record_len = u64::from_be_bytes([
+        0, 0, data[8], data[9], data[10], data[11], data[12], data[13],
+    ]);
+    if record_len != data.len() as u64 {
+        warn!(
+            "Finger image record length is {}, but it has {} bytes.",
+            record_len,
+            data.len()
+        );
+    }
+    debug!(
+        "Finger image record capture device ID: {:04x}, image acquisition level: {}",
+        // Top 4 bits are the equipment compliance.
+        dg_helpers::read_u16(data, 14).unwrap() & 0x0FFF,
+        dg_helpers::read_u16(data, 16).unwrap()
+    );
+    let finger_count = data[18];
+    let compression_algorithm = data[29];
+    let image_format = types::BiometricImageFormat::from_iso19794_4(compression_algorithm);
+    // Fields that are shared by all finger images, per-finger fields are filled in later.
+    let general_details = types::FingerDetails {
+        finger_position: types::FingerPosition::Unknown,
+        view_count: 0,
+        view_number: 0,
+        quality: 0,
+        impression_type: "".to_string(),
+        compression_algorithm: dg_helpers::code_name(
+            &COMPRESSION_ALGORITHM_NAMES,
+            compression_algorithm.into(),
+        ),
+        scale_units: dg_helpers::code_name(&SCALE_UNIT_NAMES, data[19].into()),
+        horizontal_image_resolution: dg_helpers::read_u16(data, 24).unwrap(),
+        vertical_image_resolution: dg_helpers::read_u16(data, 26).unwrap(),
+        pixel_depth: data[28],
+        image_width: 0,
+        image_height: 0,
+    };
+
+    // Finger images
+    let mut finger_start = GENERAL_HEADER_LEN;
+    for finger_index in 0..finger_count {
+        match parse_finger_image(data, finger_start, &general_details) {
+            Some((finger_block_len, image_data, details)) => {
+                representations.push((image_data, image_format, details));
+                finger_start += finger_block_len;
+            }
+            None => {
+                warn!(
+                    "Couldn't parse finger image {} of {}, it's likely truncated.",
+                    finger_index + 1,
+                    finger_count
+                );
+                break;
+            }
+        }
+    }
+    return representations;
+}
+
+/// Parses a finger image header and its image data.
+///
+/// Returns (finger image block length, image data, finger details).
+fn parse_finger_image(
+    data: &[u8],
+    finger_start: usize,
+    general_details: &types::FingerDetails,
+) -> Option<(usize, Vec<u8>, types::FingerDetails)> {
+    let finger_block_len = dg_helpers::read_u32(data, finger_start)? as usize;
+    if finger_block_len < FINGER_HEADER_LEN {
+        return None;
+    }
+    let finger_position = dg_helpers::read_u8(data, finger_start + 4)?;
+    let finger_position = match types::FingerPosition::from_repr(finger_position.into()) {
+        Some(finger_position) => finger_position,
+        None => {
+            warn!("Unknown finger position: {}", finger_position);
+            types::FingerPosition::Unknown
+        }
+    };
+    let impression_type = dg_helpers::read_u8(data, finger_start + 8)?;
+    let details = types::FingerDetails {
+        finger_position: finger_position,
+        view_count: dg_helpers::read_u8(data, finger_start + 5)?,
+        view_number: dg_helpers::read_u8(data, finger_start + 6)?,
+        quality: dg_helpers::read_u8(data, finger_start + 7)?,
+        impression_type: dg_helpers::code_name(&IMPRESSION_TYPE_NAMES, impression_type.into()),
+        image_width: dg_helpers::read_u16(data, finger_start + 9)?,
+        image_height: dg_helpers::read_u16(data, finger_start + 11)?,
+        ..general_details.clone()
+    };
+    let image_data = data
+        .get(finger_start + FINGER_HEADER_LEN..finger_start + finger_block_len)?
+        .to_vec();
+    return Some((finger_block_len, image_data, details));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finger_image_record_parsing() {
+        let image_data: Vec<u8> = vec![0xFF, 0xA0, 0xFF, 0xA1];
+        let finger_block_len = FINGER_HEADER_LEN + image_data.len();
+        let mut data: Vec<u8> = vec![];
+        data.extend_from_slice(b"FIR\x00010\x00");
+        data.extend_from_slice(
+            &((GENERAL_HEADER_LEN + finger_block_len) as u64).to_be_bytes()[2..],
+        );
+        #[rustfmt::skip]
+        data.extend_from_slice(&[
+            // Capture device ID, image acquisition level, 1 finger, pixels per inch
+            0x00, 0x00, 0x00, 0x1F, 0x01, 0x01,
+            // Scan resolution, image resolution (500x500)
+            0x01, 0xF4, 0x01, 0xF4, 0x01, 0xF4, 0x01, 0xF4,
+            // 8 bit pixels, WSQ, reserved
+            0x08, 0x02, 0x00, 0x00,
+        ]);
+        data.extend_from_slice(&(finger_block_len as u32).to_be_bytes());
+        #[rustfmt::skip]
+        data.extend_from_slice(&[
+            // Left index finger, view 1 of 1, quality 80, live-scan plain, 256x360, reserved
+            0x07, 0x01, 0x01, 0x50, 0x00, 0x01, 0x00, 0x01, 0x68, 0x00,
+        ]);
+        data.extend_from_slice(&image_data);
+
+        let representations = parse_finger_image_record(&data);
+        assert_eq!(representations.len(), 1);
+        let (parsed_image_data, image_format, details) = &representations[0];
+        assert_eq!(parsed_image_data, &image_data);
+        assert_eq!(image_format, &types::BiometricImageFormat::Wsq);
+        assert_eq!(
+            details.finger_position,
+            types::FingerPosition::LeftIndexFinger
+        );
+        assert_eq!(details.impression_type, "Live-scan plain");
+        assert_eq!(details.horizontal_image_resolution, 500);
+        assert_eq!((details.image_width, details.image_height), (256, 360));
+    }
+}
@@ -0,0 +1,314 @@
+//! ISO/IEC 19794-5:2005 facial records, as required by ICAO 9303 for the first biometric of DG2.
+use crate::dg_parsers::helpers as dg_helpers;
+use crate::types;
+use simplelog::{debug, warn};
+
+// ISO/IEC 19794-5:2005, 5.4
+const FORMAT_IDENTIFIER: [u8; 4] = *b"FAC\0";
+const VERSION_NUMBER: [u8; 4] = *b"010\0";
+const GENERAL_HEADER_LEN: usize = 14;
+// ISO/IEC 19794-5:2005, 5.5, 5.6 and 5.7
+const FACIAL_INFORMATION_LEN: usize = 20;
+const FEATURE_POINT_LEN: usize = 8;
+const IMAGE_INFORMATION_LEN: usize = 12;
+
+/// Value used for unknown gender, eye colour and hair colour
+const UNKNOWN_CODE: u8 = 0xFF;
+
+const GENDER_NAMES: [&str; 3] = ["Unspecified", "Male", "Female"];
+const EYE_COLOUR_NAMES: [&str; 8] = [
+    "Unspecified",
+    "Black",
+    "Blue",
+    "Brown",
+    "Grey",
+    "Green",
+    "Multi-coloured",
+    "Pink",
+];
+const HAIR_COLOUR_NAMES: [&str; 8] = [
+    "Unspecified",
+    "Bald",
+    "Black",
+    "Blonde",
+    "Brown",
+    "Grey",
+    "White",
+    "Red",
+];
+/// Bit 0 only signals that the other bits are specified, so it's left out here.
+const PROPERTY_NAMES: [&str; 10] = [
+    "Glasses",
+    "Moustache",
+    "Beard",
+    "Teeth visible",
+    "Blink",
+    "Mouth open",
+    "Left eye patch",
+    "Right eye patch",
+    "Dark glasses",
+    "Distorting medical condition",
+];
+const EXPRESSION_NAMES: [&str; 8] = [
+    "Unspecified",
+    "Neutral",
+    "Smile (closed jaw)",
+    "Smile (open jaw)",
+    "Raised eyebrows",
+    "Eyes looking away",
+    "Squinting",
+    "Frowning",
+];
+const FACE_IMAGE_TYPE_NAMES: [&str; 3] = ["Basic", "Full frontal", "Token frontal"];
+const COLOUR_SPACE_NAMES: [&str; 5] = [
+    "Unspecified",
+    "RGB 24 bit",
+    "YUV 422",
+    "Greyscale 8 bit",
+    "Other",
+];
+const SOURCE_TYPE_NAMES: [&str; 8] = [
+    "Unspecified",
+    "Static photo (unknown source)",
+    "Static photo (digital still)",
+    "Static photo (scan)",
+    "Video frame (unknown source)",
+    "Video frame (analogue)",
+    "Video frame (digital)",
+    "Unknown",
+];
+
+fn code_name_or_unknown(names: &[&str], code: u8) -> String {
+    match code {
+        UNKNOWN_CODE => "Unknown".to_string(),
+        _ => dg_helpers::code_name(names, code.into()),
+    }
+}
+
+/// Decodes a pose angle, which is stored in steps of 2 degrees from -180 (1) to 180 (181).
+fn decode_pose_angle(value: u8) -> Option<i16> {
+    match value {
+        1..=181 => Some((value as i16 - 91) * 2),
+        // 0 is unspecified, everything else is reserved.
+        _ => None,
+    }
+}
+
+/// Parses a facial record (facial information, feature points, image information and image data).
+///
+/// Returns (image data, image format, face details) and the length of the record.
+fn parse_facial_record_data(
+    data: &[u8],
+    record_start: usize,
+) -> Option<(
+    Vec<u8>,
+    types::BiometricImageFormat,
+    types::FaceDetails,
+    usize,
+)> {
+    // Facial information
+    let record_len = dg_helpers::read_u32(data, record_start)? as usize;
+    let feature_point_count = dg_helpers::read_u16(data, record_start + 4)? as usize;
+    let gender = dg_helpers::read_u8(data, record_start + 6)?;
+    let eye_colour = dg_helpers::read_u8(data, record_start + 7)?;
+    let hair_colour = dg_helpers::read_u8(data, record_start + 8)?;
+    let property_mask = dg_helpers::read_u24(data, record_start + 9)?;
+    let expression = dg_helpers::read_u16(data, record_start + 12)?;
+    let pose_angle = data.get(record_start + 14..record_start + 17)?;
+
+    let properties = match property_mask & 1 {
+        1 => Some(
+            PROPERTY_NAMES
+                .iter()
+                .enumerate()
+                .filter(|(index, _)| property_mask & (1 << (index + 1)) != 0)
+                .map(|(_, name)| name.to_string())
+                .collect(),
+        ),
+        _ => None,
+    };
+
+    // Feature points
+    let feature_points_start = record_start + FACIAL_INFORMATION_LEN;
+    let mut feature_points: Vec<types::FaceFeaturePoint> = vec![];
+    for feature_point_index in 0..feature_point_count {
+        let feature_point_start = feature_points_start + (FEATURE_POINT_LEN * feature_point_index);
+        let feature_point_code = dg_helpers::read_u8(data, feature_point_start + 1)?;
+        feature_points.push(types::FaceFeaturePoint {
+            point_type: dg_helpers::read_u8(data, feature_point_start)?,
+            major_code: feature_point_code >> 4,
+            minor_code: feature_point_code & 0x0F,
+            x: dg_helpers::read_u16(data, feature_point_start + 2)?,
+            y: dg_helpers::read_u16(data, feature_point_start + 4)?,
+        });
+    }
+
+    // Image information
+    let image_information_start = feature_points_start + (FEATURE_POINT_LEN * feature_point_count);
+    let face_image_type = dg_helpers::read_u8(data, image_information_start)?;
+    let image_data_type = dg_helpers::read_u8(data, image_information_start + 1)?;
+    let width = dg_helpers::read_u16(data, image_information_start + 2)?;
+    let height = dg_helpers::read_u16(data, image_information_start + 4)?;
+    let colour_space = dg_helpers::read_u8(data, image_information_start + 6)?;
+    let source_type = dg_helpers::read_u8(data, image_information_start + 7)?;
+    debug!(
+        "Facial record device type: {:04x?}, quality: {:04x?}",
+        dg_helpers::read_u16(data, image_information_start + 8),
+        dg_helpers::read_u16(data, image_information_start + 10)
+    );
+
+    // Image data, which is the rest of the record
+    let image_data_start = image_information_start + IMAGE_INFORMATION_LEN;
+    let record_end = record_start + record_len;
+    if record_end < image_data_start {
+        warn!(
+            "Facial record length ({}) is shorter than its headers.",
+            record_len
+        );
+        return None;
+    }
+    let image_data = data.get(image_data_start..record_end)?.to_vec();
+
+    let details = types::FaceDetails {
+        gender: Some(code_name_or_unknown(&GENDER_NAMES, gender)),
+        eye_colour: Some(code_name_or_unknown(&EYE_COLOUR_NAMES, eye_colour)),
+        hair_colour: Some(code_name_or_unknown(&HAIR_COLOUR_NAMES, hair_colour)),
+        properties: properties,
+        expression: Some(dg_helpers::code_name(&EXPRESSION_NAMES, expression.into())),
+        pose_angle: Some(types::FacePoseAngle {
+            yaw: decode_pose_angle(pose_angle[0]),
+            pitch: decode_pose_angle(pose_angle[1]),
+            roll: decode_pose_angle(pose_angle[2]),
+        }),
+        feature_points: feature_points,
+        face_image_type: Some(dg_helpers::code_name(
+            &FACE_IMAGE_TYPE_NAMES,
+            face_image_type.into(),
+        )),
+        colour_space: Some(dg_helpers::code_name(
+            &COLOUR_SPACE_NAMES,
+            colour_space.into(),
+        )),
+        source_type: Some(dg_helpers::code_name(
+            &SOURCE_TYPE_NAMES,
+            source_type.into(),
+        )),
+        image_width: Some(width.into()),
+        image_height: Some(height.into()),
+    };
+    return Some((
+        image_data,
+        types::BiometricImageFormat::from_iso19794_5(image_data_type),
+        details,
+        record_len,
+    ));
+}
+
+/// Parses the contents of a 5F2E biometric data block holding an ISO/IEC 19794-5 facial record.
+///
+/// Returns (image data, image format, face details) for each facial image in the record.
+pub(crate) fn parse_facial_record(
+    data: &[u8],
+) -> Vec<(Vec<u8>, types::BiometricImageFormat, types::FaceDetails)> {
+    let mut representations: Vec<(Vec<u8>, types::BiometricImageFormat, types::FaceDetails)> =
+        vec![];
+
+    // General header
+    if data.get(0..4) != Some(&FORMAT_IDENTIFIER) {
+        warn!(
+            "Biometric isn't an ISO/IEC 19794-5 facial record, skipping: {:02x?}",
+            data.get(0..4)
+        );
+        return representations;
+    }
+    // Only allow 2005 variant (this is what ICAO 9303 requires for first biometric)
+    if data.get(4..8) != Some(&VERSION_NUMBER) {
+        warn!(
+            "Biometric has unsupported version, skipping: {:02x?}",
+            data.get(4..8)
+        );
+        return representations;
+    }
+    let record_len = dg_helpers::read_u32(data, 8).unwrap_or(0) as usize;
+    if record_len != data.len() {
+        warn!(
+            "Facial record length is {}, but it has {} bytes.",
+            record_len,
+            data.len()
+        );
+    }
+    let facial_image_count = dg_helpers::read_u16(data, 12).unwrap_or(0);
+
+    let mut record_start = GENERAL_HEADER_LEN;
+    for facial_image_index in 0..facial_image_count {
+        match parse_facial_record_data(data, record_start) {
+            Some((image_data, image_format, details, facial_record_len)) => {
+                representations.push((image_data, image_format, details));
+                record_start += facial_record_len;
+            }
+            None => {
+                warn!(
+                    "Couldn't parse facial image {} of {}, it's likely truncated.",
+                    facial_image_index + 1,
+                    facial_image_count
+                );
+                break;
+            }
+        }
+    }
+    return representations;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn facial_record_parsing() {
+        let image_data: Vec<u8> = vec![0x00, 0x00, 0x00, 0x0C, 0x6A, 0x50];
+        // Facial information + 1 feature point + image information + image data
+        let facial_record_len = 20 + 8 + 12 + image_data.len();
+        let mut data: Vec<u8> = vec![];
+        data.extend_from_slice(b"FAC\x00010\x00");
+        data.extend_from_slice(&((14 + facial_record_len) as u32).to_be_bytes());
+        data.extend_from_slice(&[0x00, 0x01]);
+        data.extend_from_slice(&(facial_record_len as u32).to_be_bytes());
+        #[rustfmt::skip]
+        data.extend_from_slice(&[
+            // 1 feature point, female, blue eyes, unknown hair colour
+            0x00, 0x01, 0x02, 0x02, 0xFF,
+            // Properties specified, glasses
+            0x00, 0x00, 0x03,
+            // Neutral expression, yaw 0°, pitch 10°, roll unspecified, uncertainties
+            0x00, 0x01, 0x5B, 0x60, 0x00, 0x00, 0x00, 0x00,
+            // Feature point 12.1 at (100, 200)
+            0x01, 0xC1, 0x00, 0x64, 0x00, 0xC8, 0x00, 0x00,
+            // Full frontal, JPEG 2000, 413x531, RGB 24 bit, digital still, device type, quality
+            0x01, 0x01, 0x01, 0x9D, 0x02, 0x13, 0x01, 0x02, 0x00, 0x00, 0x00, 0x00,
+        ]);
+        data.extend_from_slice(&image_data);
+
+        let representations = parse_facial_record(&data);
+        assert_eq!(representations.len(), 1);
+        let (parsed_image_data, image_format, details) = &representations[0];
+        assert_eq!(parsed_image_data, &image_data);
+        assert_eq!(image_format, &types::BiometricImageFormat::Jpeg2000);
+        assert_eq!(details.gender, Some("Female".to_string()));
+        assert_eq!(details.hair_colour, Some("Unknown".to_string()));
+        assert_eq!(details.properties, Some(vec!["Glasses".to_string()]));
+        assert_eq!(
+            details.pose_angle,
+            Some(types::FacePoseAngle {
+                yaw: Some(0),
+                pitch: Some(10),
+                roll: None
+            })
+        );
+        assert_eq!(details.feature_points[0].major_code, 12);
+        assert_eq!(details.feature_points[0].y, 200);
+        assert_eq!(
+            (details.image_width, details.image_height),
+            (Some(413), Some(531))
+        );
+    }
+}
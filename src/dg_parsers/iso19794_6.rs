@@ -0,0 +1,237 @@
+//! ISO/IEC 19794-6:2005 iris image records (rectilinear), as used for DG4.
+use crate::dg_parsers::helpers as dg_helpers;
+use crate::types;
+use simplelog::warn;
+
+// ISO/IEC 19794-6:2005, 6.5.1
+const FORMAT_IDENTIFIER: [u8; 4] = *b"IIR\0";
+const VERSION_NUMBER: [u8; 4] = *b"010\0";
+const GENERAL_HEADER_LEN: usize = 45;
+// ISO/IEC 19794-6:2005, 6.5.2 and 6.5.3
+const SUBTYPE_HEADER_LEN: usize = 3;
+const IMAGE_HEADER_LEN: usize = 11;
+
+const ROTATION_ANGLE_UNDEFINED: u16 = 0xFFFF;
+
+const SCAN_TYPE_NAMES: [&str; 4] = [
+    "Corrected",
+    "Progressive",
+    "Interlace frame",
+    "Interlace field",
+];
+
+fn image_format_name(code: u16) -> String {
+    return match code {
+        0x0002 => "Monochrome raw",
+        0x0004 => "RGB raw",
+        0x0006 => "Monochrome JPEG",
+        0x0008 => "RGB JPEG",
+        0x000A => "Monochrome JPEG-LS",
+        0x000C => "RGB JPEG-LS",
+        0x000E => "Monochrome JPEG 2000",
+        0x0010 => "RGB JPEG 2000",
+        _ => return format!("Code {}", code),
+    }
+    .to_string();
+}
+
+/// Formats the device unique identifier, which is D/M/P followed by a serial number,
+/// MAC address or processor ID. All zeroes means there's no identifier.
+fn format_device_unique_identifier(identifier: &[u8]) -> Option<String> {
+    if identifier.iter().all(|byte| *byte == 0) {
+        return None;
+    }
+    match identifier[0] {
+        b'D' | b'M' | b'P' if identifier.is_ascii() => Some(
+            String::from_utf8_lossy(identifier)
+                .trim_end_matches('\0')
+                .to_string(),
+        ),
+        _ => Some(format!("{:02x?}", identifier)),
+    }
+}
+
+/// Parses the contents of a 5F2E biometric data block holding an ISO/IEC 19794-6 iris image record.
+///
+/// Returns (image data, image format, iris details) for each iris image in the record.
+pub(crate) fn parse_iris_image_record(
+    data: &[u8],
+) -> Vec<(Vec<u8>, types::BiometricImageFormat, types::IrisDetails)> {
+    let mut representations: Vec<(Vec<u8>, types::BiometricImageFormat, types::IrisDetails)> =
+        vec![];
+
+    // General header
+    if data.get(0..4) != Some(&FORMAT_IDENTIFIER) {
+        warn!(
+            "Biometric isn't an ISO/IEC 19794-6 iris image record, skipping: {:02x?}",
+            data.get(0..4)
+        );
+        return representations;
+    }
+    if data.get(4..8) != Some(&VERSION_NUMBER) {
+        warn!(
+            "Biometric has unsupported version, skipping: {:02x?}",
+            data.get(4..8)
+        );
+        return representations;
+    }
+    if data.len() < GENERAL_HEADER_LEN {
+        warn!("Iris image record is shorter than its general header, skipping.");
+        return representations;
+    }
+    let record_len = dg_helpers::read_u32(data, 8).unwrap() as usize;
+    if record_len != data.len() {
+        warn!(
+            "Iris image record length is {}, but it has {} bytes.",
+            record_len,
+            data.len()
+        );
+    }
+    let subtype_count = data[14];
+    let record_header_len = dg_helpers::read_u16(data, 15).unwrap() as usize;
+    let image_properties = dg_helpers::read_u16(data, 17).unwrap();
+    let iris_diameter = dg_helpers::read_u16(data, 19).unwrap();
+    let image_format = dg_helpers::read_u16(data, 21).unwrap();
+    // Fields that are shared by all iris images, per-image fields are filled in later.
+    let general_details = types::IrisDetails {
+        eye_label: types::EyeLabel::Undefined,
+        image_count: 0,
+        image_sequence_number: 0,
+        quality: 0,
+        rotation_angle: None,
+        image_type: dg_helpers::code_name(
+            &SCAN_TYPE_NAMES,
+            ((image_properties >> 4) & 0b11).into(),
+        ),
+        image_format: image_format_name(image_format),
+        capture_device_id: dg_helpers::read_u16(data, 12).unwrap(),
+        device_unique_identifier: format_device_unique_identifier(&data[29..45]),
+        iris_diameter: match iris_diameter {
+            0 => None,
+            iris_diameter => Some(iris_diameter),
+        },
+        intensity_depth: data[27],
+        image_width: dg_helpers::read_u16(data, 23).unwrap(),
+        image_height: dg_helpers::read_u16(data, 25).unwrap(),
+    };
+    let biometric_image_format = types::BiometricImageFormat::from_iso19794_6(image_format);
+
+    // Biometric subtypes (eyes), each with their own images
+    let mut offset = record_header_len;
+    for _ in 0..subtype_count {
+        let (eye_label, image_count) = match (
+            dg_helpers::read_u8(data, offset),
+            dg_helpers::read_u16(data, offset + 1),
+        ) {
+            (Some(eye_label), Some(image_count)) => (eye_label, image_count),
+            _ => {
+                warn!("Iris image record is truncated, couldn't read subtype header.");
+                return representations;
+            }
+        };
+        let eye_label = match types::EyeLabel::from_repr(eye_label.into()) {
+            Some(eye_label) => eye_label,
+            None => {
+                warn!("Unknown eye label: {}", eye_label);
+                types::EyeLabel::Undefined
+            }
+        };
+        offset += SUBTYPE_HEADER_LEN;
+
+        for _ in 0..image_count {
+            match parse_iris_image(data, offset) {
+                Some((image_len, image_data, image_sequence_number, quality, rotation_angle)) => {
+                    let details = types::IrisDetails {
+                        eye_label: eye_label,
+                        image_count: image_count,
+                        image_sequence_number: image_sequence_number,
+                        quality: quality,
+                        rotation_angle: rotation_angle,
+                        ..general_details.clone()
+                    };
+                    representations.push((image_data, biometric_image_format, details));
+                    offset += IMAGE_HEADER_LEN + image_len;
+                }
+                None => {
+                    warn!("Iris image record is truncated, couldn't read image.");
+                    return representations;
+                }
+            }
+        }
+    }
+    return representations;
+}
+
+/// Parses an iris image header and its image data.
+///
+/// Returns (image length, image data, image sequence number, quality, rotation angle).
+fn parse_iris_image(
+    data: &[u8],
+    image_start: usize,
+) -> Option<(usize, Vec<u8>, u16, u8, Option<f32>)> {
+    let image_sequence_number = dg_helpers::read_u16(data, image_start)?;
+    let quality = dg_helpers::read_u8(data, image_start + 2)?;
+    // Stored as 65536 * angle / 360, uncertainty at image_start + 5 is encoded the same way.
+    let rotation_angle = match dg_helpers::read_u16(data, image_start + 3)? {
+        ROTATION_ANGLE_UNDEFINED => None,
+        rotation_angle => Some(rotation_angle as f32 * 360.0 / 65536.0),
+    };
+    let image_len = dg_helpers::read_u32(data, image_start + 7)? as usize;
+    let image_data_start = image_start + IMAGE_HEADER_LEN;
+    let image_data = data
+        .get(image_data_start..image_data_start + image_len)?
+        .to_vec();
+    return Some((
+        image_len,
+        image_data,
+        image_sequence_number,
+        quality,
+        rotation_angle,
+    ));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn iris_image_record_parsing() {
+        let image_data: Vec<u8> = vec![0xFF, 0xD8, 0xFF, 0xD9];
+        let mut data: Vec<u8> = vec![];
+        data.extend_from_slice(b"IIR\x00010\x00");
+        let record_len =
+            GENERAL_HEADER_LEN + SUBTYPE_HEADER_LEN + IMAGE_HEADER_LEN + image_data.len();
+        data.extend_from_slice(&(record_len as u32).to_be_bytes());
+        #[rustfmt::skip]
+        data.extend_from_slice(&[
+            // Capture device ID, 1 subtype, header length
+            0x12, 0x34, 0x01, 0x00, 0x2D,
+            // Progressive scan, iris diameter 200, monochrome JPEG, 640x480, 8 bits, standard
+            0x00, 0x10, 0x00, 0xC8, 0x00, 0x06, 0x02, 0x80, 0x01, 0xE0, 0x08, 0x01,
+        ]);
+        data.extend_from_slice(b"DSERIAL1\0\0\0\0\0\0\0\0");
+        #[rustfmt::skip]
+        data.extend_from_slice(&[
+            // Left eye, 1 image
+            0x02, 0x00, 0x01,
+            // Sequence number 1, quality 90, rotation angle 90°, uncertainty undefined
+            0x00, 0x01, 0x5A, 0x40, 0x00, 0xFF, 0xFF,
+        ]);
+        data.extend_from_slice(&(image_data.len() as u32).to_be_bytes());
+        data.extend_from_slice(&image_data);
+
+        let representations = parse_iris_image_record(&data);
+        assert_eq!(representations.len(), 1);
+        let (parsed_image_data, image_format, details) = &representations[0];
+        assert_eq!(parsed_image_data, &image_data);
+        assert_eq!(image_format, &types::BiometricImageFormat::Jpeg);
+        assert_eq!(details.eye_label, types::EyeLabel::LeftEye);
+        assert_eq!(details.rotation_angle, Some(90.0));
+        assert_eq!(details.image_type, "Progressive");
+        assert_eq!(
+            details.device_unique_identifier,
+            Some("DSERIAL1".to_string())
+        );
+        assert_eq!((details.image_width, details.image_height), (640, 480));
+    }
+}
@@ -0,0 +1,289 @@
+//! ISO/IEC 39794-5:2019 extensible face image data, in its tagged binary encoding.
+use crate::dg_parsers::helpers as dg_helpers;
+use crate::helpers;
+use crate::types;
+use iso7816_tlv::ber;
+use simplelog::{debug, warn};
+
+// ISO/IEC 39794-5:2019, Annex A. FaceImageDataBlock is [APPLICATION 5].
+const FACE_IMAGE_DATA_BLOCK_TAG: u16 = 0x65;
+
+const GENDER_NAMES: [&str; 4] = ["Unknown", "Other", "Male", "Female"];
+const EYE_COLOUR_NAMES: [&str; 9] = [
+    "Unknown",
+    "Other",
+    "Black",
+    "Blue",
+    "Brown",
+    "Grey",
+    "Green",
+    "Multi-coloured",
+    "Pink",
+];
+const HAIR_COLOUR_NAMES: [&str; 16] = [
+    "Unknown",
+    "Other",
+    "Bald",
+    "Black",
+    "Blonde",
+    "Brown",
+    "Grey",
+    "Red",
+    "Blue",
+    "Green",
+    "Orange",
+    "Pink",
+    "Sandy",
+    "Auburn",
+    "White",
+    "Strawberry blonde",
+];
+const EXPRESSION_NAMES: [&str; 6] = [
+    "Neutral",
+    "Smile",
+    "Raised eyebrows",
+    "Eyes looking away",
+    "Squinting",
+    "Frowning",
+];
+const FACE_IMAGE_KIND_NAMES: [&str; 3] = ["Unknown", "MRTD", "General purpose"];
+const COLOUR_SPACE_NAMES: [&str; 7] = [
+    "Unknown",
+    "Other",
+    "RGB 24 bit",
+    "RGB 48 bit",
+    "YUV 422",
+    "Greyscale 8 bit",
+    "Greyscale 16 bit",
+];
+
+fn get_constructed(tlvs: &Vec<ber::Tlv>, tag: u16) -> Option<Vec<ber::Tlv>> {
+    return dg_helpers::tlv_get_constructed_value(helpers::get_tlv_by_tag(tlvs, tag)?);
+}
+
+fn get_integer(tlvs: &Vec<ber::Tlv>, tag: u16) -> Option<i64> {
+    return dg_helpers::tlv_get_integer(helpers::get_tlv_by_tag(tlvs, tag)?);
+}
+
+/// Gets an extensible code, which is a CHOICE of code [0] and extensionBlock [1].
+fn get_code(tlvs: &Vec<ber::Tlv>, tag: u16) -> Option<i64> {
+    let tlv = helpers::get_tlv_by_tag(tlvs, tag)?;
+    match dg_helpers::tlv_get_constructed_value(tlv) {
+        Some(choice_tlvs) => get_integer(&choice_tlvs, 0x80),
+        None => dg_helpers::tlv_get_integer(tlv),
+    }
+}
+
+/// Gets the angle value of an angle block (angleValue [0], angleUncertainty [1])
+fn get_angle(tlvs: &Vec<ber::Tlv>, tag: u16) -> Option<i16> {
+    let angle_tlvs = get_constructed(tlvs, tag)?;
+    return i16::try_from(get_integer(&angle_tlvs, 0x80)?).ok();
+}
+
+/// Finds the contents of the face image data block.
+///
+/// ICAO 9303 wraps it in 7F2E, with some issuers adding another template around it.
+fn find_face_image_data_block(tlvs: &Vec<ber::Tlv>) -> Option<Vec<ber::Tlv>> {
+    match helpers::get_tlv_by_tag(tlvs, FACE_IMAGE_DATA_BLOCK_TAG) {
+        Some(tlv) => return dg_helpers::tlv_get_constructed_value(tlv),
+        None => {}
+    }
+    for tlv in tlvs.iter() {
+        match dg_helpers::tlv_get_constructed_value(tlv) {
+            Some(inner_tlvs) => {
+                match helpers::get_tlv_by_tag(&inner_tlvs, FACE_IMAGE_DATA_BLOCK_TAG) {
+                    Some(inner_tlv) => return dg_helpers::tlv_get_constructed_value(inner_tlv),
+                    None => {}
+                }
+            }
+            None => {}
+        }
+    }
+    // Some encoders leave out the application tag, so we accept the bare block too.
+    if helpers::get_tlv_by_tag(tlvs, 0xA1).is_some() {
+        return Some(tlvs.clone());
+    }
+    return None;
+}
+
+fn parse_identity_metadata(
+    identity_metadata_tlvs: &Vec<ber::Tlv>,
+    details: &mut types::FaceDetails,
+) {
+    details.gender = get_code(identity_metadata_tlvs, 0xA0)
+        .map(|code| dg_helpers::code_name(&GENDER_NAMES, code));
+    details.eye_colour = get_code(identity_metadata_tlvs, 0xA1)
+        .map(|code| dg_helpers::code_name(&EYE_COLOUR_NAMES, code));
+    details.hair_colour = get_code(identity_metadata_tlvs, 0xA2)
+        .map(|code| dg_helpers::code_name(&HAIR_COLOUR_NAMES, code));
+
+    // Expression is a sequence of booleans, one for each expression.
+    match get_constructed(identity_metadata_tlvs, 0xA5) {
+        Some(expression_tlvs) => {
+            let expressions: Vec<&str> = EXPRESSION_NAMES
+                .iter()
+                .enumerate()
+                .filter(|(index, _)| {
+                    match helpers::get_tlv_by_tag(&expression_tlvs, 0x80 + *index as u16) {
+                        Some(tlv) => helpers::get_tlv_value_bytes(tlv).first() == Some(&0xFF),
+                        None => false,
+                    }
+                })
+                .map(|(_, name)| *name)
+                .collect();
+            details.expression = Some(expressions.join(", "));
+        }
+        None => {}
+    }
+
+    match get_constructed(identity_metadata_tlvs, 0xA6) {
+        Some(pose_angle_tlvs) => {
+            details.pose_angle = Some(types::FacePoseAngle {
+                yaw: get_angle(&pose_angle_tlvs, 0xA0),
+                pitch: get_angle(&pose_angle_tlvs, 0xA1),
+                roll: get_angle(&pose_angle_tlvs, 0xA2),
+            });
+        }
+        None => {}
+    }
+}
+
+fn parse_representation_block(
+    representation_tlvs: &Vec<ber::Tlv>,
+) -> Option<(Vec<u8>, types::BiometricImageFormat, types::FaceDetails)> {
+    let image_representation_tlvs = get_constructed(representation_tlvs, 0xA1)?;
+    // [0] is a 2D image, [1] is a 3D shape, which we don't support.
+    let image_2d_tlvs = match get_constructed(&image_representation_tlvs, 0xA0) {
+        Some(image_2d_tlvs) => image_2d_tlvs,
+        None => {
+            warn!("Face representation isn't a 2D image, skipping.");
+            return None;
+        }
+    };
+    let image_data = helpers::get_tlv_value_bytes(helpers::get_tlv_by_tag(&image_2d_tlvs, 0x80)?);
+
+    let mut details = types::FaceDetails::default();
+    let mut image_format = types::BiometricImageFormat::Reserved;
+    match get_constructed(&image_2d_tlvs, 0xA2) {
+        Some(image_information_tlvs) => {
+            match get_code(&image_information_tlvs, 0xA0) {
+                Some(code) => image_format = types::BiometricImageFormat::from_iso39794_5(code),
+                None => {}
+            }
+            details.face_image_type = get_code(&image_information_tlvs, 0xA1)
+                .map(|code| dg_helpers::code_name(&FACE_IMAGE_KIND_NAMES, code));
+            match get_constructed(&image_information_tlvs, 0xA7) {
+                Some(image_size_tlvs) => {
+                    details.image_width = get_integer(&image_size_tlvs, 0x80)
+                        .and_then(|width| u32::try_from(width).ok());
+                    details.image_height = get_integer(&image_size_tlvs, 0x81)
+                        .and_then(|height| u32::try_from(height).ok());
+                }
+                None => {}
+            }
+            details.colour_space = get_code(&image_information_tlvs, 0xA9)
+                .map(|code| dg_helpers::code_name(&COLOUR_SPACE_NAMES, code));
+        }
+        None => warn!("Face representation has no image information block."),
+    }
+
+    match get_constructed(representation_tlvs, 0xA8) {
+        Some(identity_metadata_tlvs) => {
+            parse_identity_metadata(&identity_metadata_tlvs, &mut details)
+        }
+        None => {}
+    }
+    return Some((image_data, image_format, details));
+}
+
+/// Parses the contents of a 7F2E biometric data block holding ISO/IEC 39794-5 face data.
+///
+/// Returns (image data, image format, face details) for each 2D representation.
+pub(crate) fn parse_face_image_data_block(
+    data: &[u8],
+) -> Vec<(Vec<u8>, types::BiometricImageFormat, types::FaceDetails)> {
+    let mut representations: Vec<(Vec<u8>, types::BiometricImageFormat, types::FaceDetails)> =
+        vec![];
+    let tlvs = ber::Tlv::parse_all(data);
+    let face_image_data_block_tlvs = match find_face_image_data_block(&tlvs) {
+        Some(block_tlvs) => block_tlvs,
+        None => {
+            warn!("Couldn't find an ISO/IEC 39794-5 face image data block.");
+            return representations;
+        }
+    };
+    match get_constructed(&face_image_data_block_tlvs, 0xA0) {
+        Some(version_tlvs) => debug!(
+            "ISO/IEC 39794-5 version: generation {:?}, year {:?}",
+            get_integer(&version_tlvs, 0x80),
+            get_integer(&version_tlvs, 0x81)
+        ),
+        None => {}
+    }
+
+    let representation_blocks = match get_constructed(&face_image_data_block_tlvs, 0xA1) {
+        Some(representation_blocks) => representation_blocks,
+        None => {
+            warn!("ISO/IEC 39794-5 face image data block has no representations.");
+            return representations;
+        }
+    };
+    for representation_block in representation_blocks.iter() {
+        let representation_tlvs = match dg_helpers::tlv_get_constructed_value(representation_block)
+        {
+            Some(representation_tlvs) => representation_tlvs,
+            None => continue,
+        };
+        match parse_representation_block(&representation_tlvs) {
+            Some(representation) => representations.push(representation),
+            None => warn!("Couldn't parse a face representation, skipping."),
+        }
+    }
+    return representations;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn face_image_data_block_parsing() {
+        #[rustfmt::skip]
+        let data: Vec<u8> = vec![
+            0x65, 0x48,
+                // Version block: generation 3, year 2019
+                0xA0, 0x07, 0x80, 0x01, 0x03, 0x81, 0x02, 0x07, 0xE3,
+                0xA1, 0x3D, 0x30, 0x3B,
+                    // Representation ID
+                    0x80, 0x01, 0x00,
+                    0xA1, 0x1E, 0xA0, 0x1C,
+                        // Image data
+                        0x80, 0x04, 0xFF, 0xD8, 0xFF, 0xD9,
+                        0xA2, 0x14,
+                            // JPEG, MRTD, 640x480
+                            0xA0, 0x03, 0x80, 0x01, 0x02,
+                            0xA1, 0x03, 0x80, 0x01, 0x01,
+                            0xA7, 0x08, 0x80, 0x02, 0x02, 0x80, 0x81, 0x02, 0x01, 0xE0,
+                    0xA8, 0x16,
+                        // Female, brown eyes, neutral expression, yaw -2°
+                        0xA0, 0x03, 0x80, 0x01, 0x03,
+                        0xA1, 0x03, 0x80, 0x01, 0x04,
+                        0xA5, 0x03, 0x80, 0x01, 0xFF,
+                        0xA6, 0x05, 0xA0, 0x03, 0x80, 0x01, 0xFE,
+        ];
+        let representations = parse_face_image_data_block(&data);
+        assert_eq!(representations.len(), 1);
+        let (image_data, image_format, details) = &representations[0];
+        assert_eq!(image_data, &vec![0xFF, 0xD8, 0xFF, 0xD9]);
+        assert_eq!(image_format, &types::BiometricImageFormat::Jpeg);
+        assert_eq!(details.face_image_type, Some("MRTD".to_string()));
+        assert_eq!(details.gender, Some("Female".to_string()));
+        assert_eq!(details.eye_colour, Some("Brown".to_string()));
+        assert_eq!(details.expression, Some("Neutral".to_string()));
+        assert_eq!(
+            (details.image_width, details.image_height),
+            (Some(640), Some(480))
+        );
+        assert_eq!(details.pose_angle.as_ref().unwrap().yaw, Some(-2));
+    }
+}
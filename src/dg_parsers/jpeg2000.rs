@@ -0,0 +1,174 @@
+//! JPEG 2000 to PNG conversion, as few image viewers can display JPEG 2000.
+use jpeg2k::{ImageData, ImagePixelData};
+use png::{BitDepth, ColorType};
+use simplelog::{info, warn};
+use std::{fs, io, path::Path};
+
+/// PNG stores 16 bit samples as big-endian.
+fn to_be_bytes(pixels: &[u16]) -> Vec<u8> {
+    return pixels
+        .iter()
+        .flat_map(|pixel| pixel.to_be_bytes())
+        .collect();
+}
+
+/// Encodes decoded JPEG 2000 pixels as PNG.
+fn encode_png(image_data: &ImageData) -> Result<Vec<u8>, png::EncodingError> {
+    let (color_type, bit_depth, pixels) = match &image_data.data {
+        ImagePixelData::L8(pixels) => (ColorType::Grayscale, BitDepth::Eight, pixels.clone()),
+        ImagePixelData::La8(pixels) => (ColorType::GrayscaleAlpha, BitDepth::Eight, pixels.clone()),
+        ImagePixelData::Rgb8(pixels) => (ColorType::Rgb, BitDepth::Eight, pixels.clone()),
+        ImagePixelData::Rgba8(pixels) => (ColorType::Rgba, BitDepth::Eight, pixels.clone()),
+        ImagePixelData::L16(pixels) => {
+            (ColorType::Grayscale, BitDepth::Sixteen, to_be_bytes(pixels))
+        }
+        ImagePixelData::La16(pixels) => (
+            ColorType::GrayscaleAlpha,
+            BitDepth::Sixteen,
+            to_be_bytes(pixels),
+        ),
+        ImagePixelData::Rgb16(pixels) => (ColorType::Rgb, BitDepth::Sixteen, to_be_bytes(pixels)),
+        ImagePixelData::Rgba16(pixels) => (ColorType::Rgba, BitDepth::Sixteen, to_be_bytes(pixels)),
+    };
+
+    let mut png_data: Vec<u8> = vec![];
+    let mut encoder = png::Encoder::new(&mut png_data, image_data.width, image_data.height);
+    encoder.set_color(color_type);
+    encoder.set_depth(bit_depth);
+    let mut writer = encoder.write_header()?;
+    writer.write_image_data(&pixels)?;
+    writer.finish()?;
+    return Ok(png_data);
+}
+
+/// Decodes a JPEG 2000 image (JP2 or a bare codestream) into its pixels.
+fn decode(data: &[u8]) -> Option<ImageData> {
+    let image = match jpeg2k::Image::from_bytes(data) {
+        Ok(image) => image,
+        Err(err) => {
+            warn!("Couldn't decode JPEG 2000 image: {}", err);
+            return None;
+        }
+    };
+    match image.get_pixels(None) {
+        Ok(image_data) => return Some(image_data),
+        Err(err) => {
+            warn!("Couldn't get JPEG 2000 image pixels: {}", err);
+            return None;
+        }
+    }
+}
+
+/// Decodes a JPEG 2000 image into 8 bit RGB pixels, returning (width, height, pixels).
+#[cfg(feature = "preview")]
+pub(crate) fn decode_rgb(data: &[u8]) -> Option<(usize, usize, Vec<u8>)> {
+    let image_data = decode(data)?;
+    let pixels: Vec<u8> = match &image_data.data {
+        ImagePixelData::L8(pixels) => pixels.iter().flat_map(|luma| [*luma; 3]).collect(),
+        ImagePixelData::La8(pixels) => pixels
+            .chunks_exact(2)
+            .flat_map(|pixel| [pixel[0]; 3])
+            .collect(),
+        ImagePixelData::Rgb8(pixels) => pixels.clone(),
+        ImagePixelData::Rgba8(pixels) => pixels
+            .chunks_exact(4)
+            .flat_map(|pixel| [pixel[0], pixel[1], pixel[2]])
+            .collect(),
+        ImagePixelData::L16(pixels) => pixels
+            .iter()
+            .flat_map(|luma| [(luma >> 8) as u8; 3])
+            .collect(),
+        ImagePixelData::La16(pixels) => pixels
+            .chunks_exact(2)
+            .flat_map(|pixel| [(pixel[0] >> 8) as u8; 3])
+            .collect(),
+        ImagePixelData::Rgb16(pixels) => pixels.iter().map(|sample| (sample >> 8) as u8).collect(),
+        ImagePixelData::Rgba16(pixels) => pixels
+            .chunks_exact(4)
+            .flat_map(|pixel| [pixel[0], pixel[1], pixel[2]].map(|sample| (sample >> 8) as u8))
+            .collect(),
+    };
+    return Some((
+        image_data.width as usize,
+        image_data.height as usize,
+        pixels,
+    ));
+}
+
+/// Converts a JPEG 2000 image (JP2 or a bare codestream) to PNG.
+pub(crate) fn to_png(data: &[u8]) -> Option<Vec<u8>> {
+    let image_data = decode(data)?;
+    match encode_png(&image_data) {
+        Ok(png_data) => return Some(png_data),
+        Err(err) => {
+            warn!("Couldn't encode JPEG 2000 image as PNG: {}", err);
+            return None;
+        }
+    }
+}
+
+/// Saves a PNG conversion of the JPEG 2000 image next to the given path of the original.
+pub(crate) fn dump_as_png(data: &[u8], image_path: &Path) -> Result<(), io::Error> {
+    let png_data = match to_png(data) {
+        Some(png_data) => png_data,
+        None => {
+            warn!("Couldn't convert JPEG 2000 image to PNG, only saved the original.");
+            return Ok(());
+        }
+    };
+    let png_path = image_path.with_extension("png");
+
+    // Create, write to and sync file.
+    let mut f = fs::File::create(&png_path)?;
+    io::Write::write_all(&mut f, &png_data)?;
+    f.sync_all()?;
+
+    info!(
+        "<magenta>Saved PNG conversion to {}</>",
+        &png_path.to_string_lossy()
+    );
+    return Ok(());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn jpeg2000_to_png_conversion() {
+        // Lossless 17x11 RGB codestream, with red growing along x and green along y.
+        #[rustfmt::skip]
+        let data: Vec<u8> = vec![
+            0xFF, 0x4F, 0xFF, 0x51, 0x00, 0x2F, 0x00, 0x00, 0x00, 0x00, 0x00, 0x11,
+            0x00, 0x00, 0x00, 0x0B, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            0x00, 0x00, 0x00, 0x11, 0x00, 0x00, 0x00, 0x0B, 0x00, 0x00, 0x00, 0x00,
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x03, 0x07, 0x01, 0x01, 0x07, 0x01, 0x01,
+            0x07, 0x01, 0x01, 0xFF, 0x52, 0x00, 0x0C, 0x00, 0x00, 0x00, 0x01, 0x00,
+            0x01, 0x04, 0x04, 0x00, 0x01, 0xFF, 0x5C, 0x00, 0x07, 0x40, 0x40, 0x48,
+            0x48, 0x50, 0xFF, 0x64, 0x00, 0x25, 0x00, 0x01, 0x43, 0x72, 0x65, 0x61,
+            0x74, 0x65, 0x64, 0x20, 0x62, 0x79, 0x20, 0x4F, 0x70, 0x65, 0x6E, 0x4A,
+            0x50, 0x45, 0x47, 0x20, 0x76, 0x65, 0x72, 0x73, 0x69, 0x6F, 0x6E, 0x20,
+            0x32, 0x2E, 0x35, 0x2E, 0x32, 0xFF, 0x90, 0x00, 0x0A, 0x00, 0x00, 0x00,
+            0x00, 0x00, 0x63, 0x00, 0x01, 0xFF, 0x93, 0xDF, 0x81, 0x28, 0x11, 0x69,
+            0x9B, 0x31, 0xCD, 0x40, 0x64, 0xEC, 0x2E, 0x99, 0xB5, 0x9D, 0xEB, 0x89,
+            0xC9, 0x6C, 0xD4, 0x1E, 0xFE, 0xC3, 0xE0, 0x98, 0x21, 0x88, 0x24, 0x71,
+            0xFD, 0x2B, 0x2B, 0xBC, 0xDF, 0x37, 0xE8, 0x48, 0xAA, 0x98, 0x7F, 0xDF,
+            0x80, 0xC0, 0x12, 0x8D, 0x94, 0x79, 0xB0, 0xE6, 0x6D, 0x8F, 0x60, 0xFE,
+            0xC4, 0x51, 0xF7, 0x81, 0xE4, 0x94, 0xC6, 0x58, 0xA4, 0xD5, 0x62, 0x3E,
+            0x40, 0x3F, 0xC3, 0xE7, 0x18, 0x11, 0x50, 0x54, 0x8A, 0x26, 0x4D, 0xF0,
+            0x00, 0x03, 0x09, 0x09, 0x7F, 0x80, 0x80, 0x80, 0xFF, 0xD9,
+        ];
+        let png_data = to_png(&data).unwrap();
+
+        let mut reader = png::Decoder::new(io::Cursor::new(png_data))
+            .read_info()
+            .unwrap();
+        let mut pixels = vec![0; reader.output_buffer_size().unwrap()];
+        let info = reader.next_frame(&mut pixels).unwrap();
+        assert_eq!((info.width, info.height), (17, 11));
+        assert_eq!(info.color_type, ColorType::Rgb);
+        // Pixel (3, 2)
+        let offset = (2 * 17 + 3) * 3;
+        assert_eq!(&pixels[offset..offset + 3], &[45, 40, 100]);
+    }
+}
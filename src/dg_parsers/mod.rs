@@ -1,9 +1,24 @@
+pub mod consistency;
+pub mod ef_atr_info;
 pub mod ef_com;
 pub mod ef_dg1;
 pub mod ef_dg11;
 pub mod ef_dg12;
+pub mod ef_dg13;
+pub mod ef_dg16;
 pub mod ef_dg2_3_4;
 pub mod ef_dg5;
 pub mod ef_dg7;
+pub mod ef_dir;
+pub mod ef_sod;
 pub mod generic;
 pub mod helpers;
+pub mod iso19794_4;
+pub mod iso19794_5;
+pub mod iso19794_6;
+pub mod iso39794_5;
+#[cfg(feature = "jpeg2000")]
+pub mod jpeg2000;
+#[cfg(feature = "vds")]
+pub mod vds;
+pub mod wsq;
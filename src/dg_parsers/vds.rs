@@ -0,0 +1,93 @@
+use crate::dg_parsers::helpers as dg_helpers;
+use crate::types;
+use simplelog::info;
+
+impl types::VisibleDigitalSeal {
+    #[cfg(feature = "cli")]
+    pub fn fancy_print(&self) {
+        info!("");
+        info!("{}", dg_helpers::pad_section_title("VDS"));
+        info!(
+            "{}",
+            dg_helpers::pad_section_subtitle("Visible Digital Seal")
+        );
+        info!("");
+        dg_helpers::print_string_element("VDS Version", &self.header.version.to_string());
+        dg_helpers::print_string_element("Document Type", &format!("{:?}", self.document_type()));
+        dg_helpers::print_string_element("Issuing Country", &self.header.issuing_country);
+        dg_helpers::print_string_element("Signer Identifier", &self.header.signer_identifier);
+        dg_helpers::print_string_element(
+            "Certificate Reference",
+            &self.header.certificate_reference,
+        );
+        dg_helpers::print_option_string_element_as_dg_date(
+            "Document Issue Date",
+            &self.header.document_issue_date,
+        );
+        dg_helpers::print_option_string_element_as_dg_date(
+            "Signature Creation Date",
+            &self.header.signature_creation_date,
+        );
+
+        match self.document_type() {
+            types::VdsDocumentType::Visa => self.fancy_print_visa_features(),
+            _ => {
+                match self.raw_mrz() {
+                    Some(mrz_text) => dg_helpers::print_string_element("MRZ", &mrz_text),
+                    None => {}
+                }
+                for feature in self.features.iter() {
+                    dg_helpers::print_option_binary_element(
+                        &format!("Feature 0x{:02X}", feature.tag),
+                        &Some(&feature.value),
+                    );
+                }
+            }
+        }
+        dg_helpers::print_option_binary_element("Signature", &self.signature);
+        info!("");
+    }
+
+    #[cfg(feature = "cli")]
+    fn fancy_print_visa_features(&self) {
+        // ICAO 9303 part 13, edition 8, 4.1
+        match self.mrz() {
+            Some(mrz_obj) => mrz_obj.fancy_print(),
+            None => match self.raw_mrz() {
+                Some(mrz_text) => dg_helpers::print_string_element("MRZ", &mrz_text),
+                None => {}
+            },
+        }
+        match self.get_feature(0x03) {
+            Some(entries) if entries.len() == 1 => {
+                let entries_text = match entries[0] {
+                    0 => "Unlimited".to_string(),
+                    count => count.to_string(),
+                };
+                dg_helpers::print_string_element("Number of Entries", &entries_text);
+            }
+            _ => {}
+        }
+        match self.get_feature(0x04) {
+            Some(duration) if duration.len() == 3 => {
+                dg_helpers::print_string_element(
+                    "Duration of Stay",
+                    &format!(
+                        "{} days, {} months, {} years",
+                        duration[0], duration[1], duration[2]
+                    ),
+                );
+            }
+            _ => {}
+        }
+        match self.get_feature(0x05) {
+            Some(passport_number) => dg_helpers::print_string_element(
+                "Passport Number",
+                &dg_helpers::remove_mrz_padding(&types::c40_decode(passport_number)),
+            ),
+            None => {}
+        }
+        dg_helpers::print_option_binary_element("Visa Type", &self.get_feature(0x06));
+        dg_helpers::print_option_binary_element("Additional Feature", &self.get_feature(0x07));
+    }
+}
@@ -2,25 +2,50 @@ use iso7816_tlv::ber;
 use simplelog::warn;
 use std::cmp::max;
 use std::collections::HashMap;
-use std::path::PathBuf;
+use std::error::Error;
+use std::ffi::OsStr;
+use std::path::{Path, PathBuf};
 use std::time::{SystemTime, UNIX_EPOCH};
+use std::{fs, io};
 
 use crate::iso7816;
 use crate::smartcard_abstractions::Smartcard;
 use crate::types;
 use crate::types::ParsedDataGroup;
 
-pub fn asn1_parse_len(data: Vec<u8>) -> (u8, u32) {
-    let result: (u8, u32) = match data[0] {
-        0..=0x7f => (1, data[0].into()),
-        0x80 => (1, 0u32), // TODO: indefinite amount
-        0x81 => (2, data[1].into()),
-        0x82 => (3, u32::from_be_bytes([0, 0, data[1], data[2]])),
-        0x83 => (4, u32::from_be_bytes([0, data[1], data[2], data[3]])),
-        0x84 => (5, u32::from_be_bytes([data[1], data[2], data[3], data[4]])),
-        0x84.. => (0, 0u32),
-    };
-    return result;
+/// Parses a BER length field, and returns (length of the length field, length).
+///
+/// Returns None if the data is too short for the length field, or if the length is indefinite.
+pub fn asn1_parse_len(data: &[u8]) -> Option<(u8, u32)> {
+    match *data.first()? {
+        len @ 0..=0x7F => return Some((1, len.into())),
+        len @ 0x81..=0x84 => {
+            let len_bytes_len = (len & 0x7F) as usize;
+            let len_bytes = data.get(1..1 + len_bytes_len)?;
+            let value_len = len_bytes
+                .iter()
+                .fold(0u32, |value_len, byte| (value_len << 8) | *byte as u32);
+            return Some((1 + len_bytes_len as u8, value_len));
+        }
+        // 0x80 is indefinite length, the rest are reserved
+        _ => return None,
+    }
+}
+
+/// Parses the tag and length of a BER-TLV header, and returns the length of the whole TLV.
+///
+/// Returns None if the data is too short for the header, or if the length is indefinite.
+pub fn asn1_parse_tlv_len(data: &[u8]) -> Option<usize> {
+    // If bits 5-1 of the first byte are all set, more tag bytes follow, with b8 set on all but the last.
+    let mut tag_len = 1;
+    if data.first()? & 0x1F == 0x1F {
+        while data.get(tag_len)? & 0x80 != 0 {
+            tag_len += 1;
+        }
+        tag_len += 1;
+    }
+    let (len_field_len, value_len) = asn1_parse_len(data.get(tag_len..)?)?;
+    return Some(tag_len + len_field_len as usize + value_len as usize);
 }
 
 pub fn get_tlv_value_bytes(input_tlv: &ber::Tlv) -> Vec<u8> {
@@ -97,37 +122,49 @@ pub fn get_tlv_by_tag(tlvs: &Vec<ber::Tlv>, desired_tag_number: u16) -> Option<&
 
 /// Selects, reads, parses and dumps file
 ///
-/// Returns (dg_info, file_read, parsed_data)
+/// Returns (dg_info, file_read, parsed_data), or an error if the card stopped answering.
 pub fn read_file_by_name<'a>(
     smartcard: &'a mut Box<impl Smartcard + ?Sized>,
     file: types::DataGroupEnum,
     filename_distinguisher: &String,
     base_dump_path: &Option<PathBuf>,
-) -> (
-    &'a types::DataGroup,
-    Option<Vec<u8>>,
-    Option<ParsedDataGroup>,
-) {
+    apdu_limits: &iso7816::ApduLimits,
+) -> Result<
+    (
+        &'a types::DataGroup,
+        Option<Vec<u8>>,
+        Option<ParsedDataGroup>,
+    ),
+    Box<dyn Error>,
+> {
     let dg_info = &types::DATA_GROUPS[file as usize];
-    let (file_read, parsed_data) =
-        read_file(smartcard, &dg_info, filename_distinguisher, base_dump_path);
-    return (dg_info, file_read, parsed_data);
+    let (file_read, parsed_data) = read_file(
+        smartcard,
+        &dg_info,
+        filename_distinguisher,
+        base_dump_path,
+        apdu_limits,
+    )?;
+    return Ok((dg_info, file_read, parsed_data));
 }
 
 /// Selects, reads, parses and dumps file
 ///
-/// Returns (file_read, parsed_data)
+/// Returns (file_read, parsed_data), or an error if the card stopped answering.
 pub fn read_file(
     smartcard: &mut Box<impl Smartcard + ?Sized>,
     dg_info: &types::DataGroup,
     filename_distinguisher: &String,
     base_dump_path: &Option<PathBuf>,
-) -> (Option<Vec<u8>>, Option<ParsedDataGroup>) {
+    apdu_limits: &iso7816::ApduLimits,
+) -> Result<(Option<Vec<u8>>, Option<ParsedDataGroup>), Box<dyn Error>> {
     return secure_read_file(
         smartcard,
         &dg_info,
         filename_distinguisher,
         base_dump_path,
+        apdu_limits,
+        true,
         false,
         &mut 0,
         &vec![],
@@ -137,55 +174,72 @@ pub fn read_file(
 
 /// Selects, reads, parses and dumps file with secure comms
 ///
-/// Returns (dg_info, file_read, parsed_data)
+/// Returns (dg_info, file_read, parsed_data), or an error if the card stopped answering.
 pub fn secure_read_file_by_name<'a>(
     smartcard: &'a mut Box<impl Smartcard + ?Sized>,
     file: types::DataGroupEnum,
     filename_distinguisher: &String,
     base_dump_path: &Option<PathBuf>,
+    apdu_limits: &iso7816::ApduLimits,
     secure_comms: bool,
     ssc: &mut u64,
     ks_enc: &Vec<u8>,
     ks_mac: &Vec<u8>,
-) -> (
-    &'a types::DataGroup,
-    Option<Vec<u8>>,
-    Option<ParsedDataGroup>,
-) {
+) -> Result<
+    (
+        &'a types::DataGroup,
+        Option<Vec<u8>>,
+        Option<ParsedDataGroup>,
+    ),
+    Box<dyn Error>,
+> {
     let dg_info = &types::DATA_GROUPS[file as usize];
     let (file_read, parsed_data) = secure_read_file(
         smartcard,
         &dg_info,
         filename_distinguisher,
         base_dump_path,
+        apdu_limits,
+        true,
         secure_comms,
         ssc,
         ks_enc,
         ks_mac,
-    );
-    return (dg_info, file_read, parsed_data);
+    )?;
+    return Ok((dg_info, file_read, parsed_data));
 }
 
 /// Selects, reads, parses and dumps file with secure comms
 ///
-/// Returns (file_read, parsed_data)
+/// Parsed data is only printed if print_data is set.
+///
+/// Returns (file_read, parsed_data), or an error if the card stopped answering.
 pub fn secure_read_file(
     smartcard: &mut Box<impl Smartcard + ?Sized>,
     dg_info: &types::DataGroup,
     filename_distinguisher: &String,
     base_dump_path: &Option<PathBuf>,
+    apdu_limits: &iso7816::ApduLimits,
+    print_data: bool,
     secure_comms: bool,
     ssc: &mut u64,
     ks_enc: &Vec<u8>,
     ks_mac: &Vec<u8>,
-) -> (Option<Vec<u8>>, Option<ParsedDataGroup>) {
-    let file_read =
-        iso7816::select_and_read_file(smartcard, dg_info, secure_comms, ssc, ks_enc, ks_mac);
+) -> Result<(Option<Vec<u8>>, Option<ParsedDataGroup>), Box<dyn Error>> {
+    let file_read = iso7816::select_and_read_file(
+        smartcard,
+        dg_info,
+        apdu_limits,
+        secure_comms,
+        ssc,
+        ks_enc,
+        ks_mac,
+    )?;
     let mut parsed_data: Option<ParsedDataGroup> = None;
     match file_read {
         Some(ref file_data) => {
-            parsed_data = (dg_info.parser)(&file_data, &dg_info, true);
-            let filename = format!("{}-{}", filename_distinguisher, dg_info.name).replace(".", "_");
+            parsed_data = (dg_info.parser)(&file_data, &dg_info, print_data);
+            let filename = dump_filename(filename_distinguisher, dg_info);
 
             if base_dump_path.is_some() {
                 let _ = (dg_info.dumper)(
@@ -198,7 +252,66 @@ pub fn secure_read_file(
         }
         None => {}
     }
-    return (file_read, parsed_data);
+    return Ok((file_read, parsed_data));
+}
+
+/// Returns the name a data group is dumped with, without the extension.
+pub fn dump_filename(filename_distinguisher: &String, dg_info: &types::DataGroup) -> String {
+    return format!("{}-{}", filename_distinguisher, dg_info.name).replace(['.', '/'], "_");
+}
+
+/// A raw data group file, as dumped by `--dump`.
+pub struct DumpedFile {
+    pub filename_distinguisher: String,
+    pub dg_info: &'static types::DataGroup,
+    pub path: PathBuf,
+}
+
+/// Finds the raw data group files in a dump directory.
+///
+/// Files are sorted by document (filename distinguisher), and then in the order of DATA_GROUPS.
+pub fn find_dumped_files(path: &Path) -> Result<Vec<DumpedFile>, io::Error> {
+    let mut dumped_files: Vec<(usize, DumpedFile)> = vec![];
+    for entry in fs::read_dir(path)? {
+        let file_path = entry?.path();
+        if !file_path.is_file() || file_path.extension() != Some(OsStr::new("bin")) {
+            continue;
+        }
+        let file_stem = match file_path
+            .file_stem()
+            .and_then(|file_stem| file_stem.to_str())
+        {
+            Some(file_stem) => file_stem.to_string(),
+            None => continue,
+        };
+        for (dg_index, dg_info) in types::DATA_GROUPS.iter().enumerate() {
+            // With an empty distinguisher, this is just the part after it (like "-EF_DG1").
+            let dg_suffix = dump_filename(&String::new(), dg_info);
+            match file_stem.strip_suffix(&dg_suffix) {
+                Some(filename_distinguisher) => {
+                    dumped_files.push((
+                        dg_index,
+                        DumpedFile {
+                            filename_distinguisher: filename_distinguisher.to_string(),
+                            dg_info: dg_info,
+                            path: file_path,
+                        },
+                    ));
+                    break;
+                }
+                None => {}
+            }
+        }
+    }
+    dumped_files.sort_by(|(a_index, a), (b_index, b)| {
+        a.filename_distinguisher
+            .cmp(&b.filename_distinguisher)
+            .then(a_index.cmp(b_index))
+    });
+    return Ok(dumped_files
+        .into_iter()
+        .map(|(_, dumped_file)| dumped_file)
+        .collect());
 }
 
 /// Get the current unix time.
@@ -211,3 +324,27 @@ pub(crate) fn unix_time() -> u64 {
         .unwrap()
         .as_secs();
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tlv_len_parsing() {
+        // DG1 with a short length
+        assert_eq!(asn1_parse_tlv_len(&[0x61, 0x5B, 0x5F, 0x1F]), Some(0x5D));
+        // DG2 with a 3 byte length
+        assert_eq!(
+            asn1_parse_tlv_len(&[0x75, 0x83, 0x01, 0x23, 0x45, 0x7F, 0x61]),
+            Some(5 + 0x012345)
+        );
+        // Two byte tag
+        assert_eq!(
+            asn1_parse_tlv_len(&[0x7F, 0x61, 0x82, 0x01, 0x00]),
+            Some(0x105)
+        );
+        // Indefinite length, and not enough data for the header
+        assert_eq!(asn1_parse_tlv_len(&[0x30, 0x80, 0x00]), None);
+        assert_eq!(asn1_parse_tlv_len(&[0x7F, 0x61, 0x82, 0x01]), None);
+    }
+}
@@ -6,8 +6,9 @@ use rand::Rng;
 use retail_mac::{Mac, RetailMac};
 use sha1::{Digest, Sha1};
 use simplelog::{debug, info};
+use std::error::Error;
 
-use crate::{iso7816, smartcard_abstractions::Smartcard};
+use crate::{iso7816, smartcard_abstractions::Smartcard, types};
 
 type RetailMacDes = RetailMac<des::Des>;
 type TDesCbcEnc = cbc::Encryptor<des::TdesEde2>;
@@ -15,6 +16,36 @@ type TDesCbcDec = cbc::Decryptor<des::TdesEde2>;
 
 const TDES_IV: [u8; 8] = [0x00u8; 8];
 pub static AID_MRTD_LDS1: [u8; 7] = [0xA0, 0x00, 0x00, 0x02, 0x47, 0x10, 0x01];
+// ICAO 9303 part 10, edition 8, 3.10
+pub static AID_LDS2_TRAVEL_RECORDS: [u8; 7] = [0xA0, 0x00, 0x00, 0x02, 0x47, 0x20, 0x01];
+pub static AID_LDS2_VISA_RECORDS: [u8; 7] = [0xA0, 0x00, 0x00, 0x02, 0x47, 0x20, 0x02];
+pub static AID_LDS2_ADDITIONAL_BIOMETRICS: [u8; 7] = [0xA0, 0x00, 0x00, 0x02, 0x47, 0x20, 0x03];
+// BSI TR-03110 part 4
+pub static AID_EID: [u8; 9] = [0xE8, 0x07, 0x04, 0x00, 0x7F, 0x00, 0x07, 0x03, 0x02];
+pub static AID_ESIGN: [u8; 10] = [0xA0, 0x00, 0x00, 0x01, 0x67, 0x45, 0x53, 0x49, 0x47, 0x4E];
+/// Applications passauf can read, in the order they're read in.
+pub static SUPPORTED_APPLICATIONS: [&[u8]; 1] = [&AID_MRTD_LDS1];
+
+/// Returns the name of a known application, by its AID.
+pub fn get_application_name(aid: &[u8]) -> Option<&'static str> {
+    let known_applications: [(&[u8], &'static str); 6] = [
+        (&AID_MRTD_LDS1, "eMRTD (LDS1)"),
+        (&AID_LDS2_TRAVEL_RECORDS, "Travel Records (LDS2)"),
+        (&AID_LDS2_VISA_RECORDS, "Visa Records (LDS2)"),
+        (
+            &AID_LDS2_ADDITIONAL_BIOMETRICS,
+            "Additional Biometrics (LDS2)",
+        ),
+        (&AID_EID, "eID"),
+        (&AID_ESIGN, "eSign"),
+    ];
+    for (known_aid, name) in known_applications {
+        if known_aid == aid {
+            return Some(name);
+        }
+    }
+    return None;
+}
 
 /// Calculates MRZ check digits according to ICAO 9303 p3
 ///
@@ -67,6 +98,40 @@ pub fn kdf_sha1(shared_secret: &[u8], counter: u32) -> Vec<u8> {
     return keydata.to_vec();
 }
 
+// RFC 3279 and RFC 5758, hash algorithms used by EF.SOD
+pub const OID_SHA1: &str = "1.3.14.3.2.26";
+pub const OID_SHA224: &str = "2.16.840.1.101.3.4.2.4";
+pub const OID_SHA256: &str = "2.16.840.1.101.3.4.2.1";
+pub const OID_SHA384: &str = "2.16.840.1.101.3.4.2.2";
+pub const OID_SHA512: &str = "2.16.840.1.101.3.4.2.3";
+
+/// Returns the name of a hash algorithm, by its OID.
+pub fn get_hash_algorithm_name(oid: &str) -> Option<&'static str> {
+    return match oid {
+        OID_SHA1 => Some("SHA-1"),
+        OID_SHA224 => Some("SHA-224"),
+        OID_SHA256 => Some("SHA-256"),
+        OID_SHA384 => Some("SHA-384"),
+        OID_SHA512 => Some("SHA-512"),
+        _ => None,
+    };
+}
+
+/// Hashes data with the hash algorithm with the given OID.
+///
+/// Returns None if the algorithm isn't supported.
+pub fn hash_with_oid(oid: &str, data: &[u8]) -> Option<Vec<u8>> {
+    // sha2 is on an older digest release than sha1, so its Digest trait is used by its full path.
+    return match oid {
+        OID_SHA1 => Some(Sha1::digest(data).to_vec()),
+        OID_SHA224 => Some(<sha2::Sha224 as sha2::Digest>::digest(data).to_vec()),
+        OID_SHA256 => Some(<sha2::Sha256 as sha2::Digest>::digest(data).to_vec()),
+        OID_SHA384 => Some(<sha2::Sha384 as sha2::Digest>::digest(data).to_vec()),
+        OID_SHA512 => Some(<sha2::Sha512 as sha2::Digest>::digest(data).to_vec()),
+        _ => None,
+    };
+}
+
 /// Applies Padding Method 2 based on ISO 9797-1.
 ///
 /// Takes the data and returns a new Vec with the appropriate padding.
@@ -84,8 +149,10 @@ pub fn padding_method_2_pad(input: &Vec<u8>) -> Vec<u8> {
 /// Undoes Padding Method 2 based on ISO 9797-1.
 ///
 /// Takes the data and returns a new Vec without the padding.
-pub fn padding_method_2_unpad(input: &Vec<u8>) -> Vec<u8> {
-    return block_padding::Iso7816::raw_unpad(input).unwrap().to_vec();
+pub fn padding_method_2_unpad(input: &Vec<u8>) -> Option<Vec<u8>> {
+    return block_padding::Iso7816::raw_unpad(input)
+        .ok()
+        .map(|data| data.to_vec());
 }
 
 /// Applies Retail Mac based on ISO 9797-1.
@@ -211,17 +278,24 @@ pub fn calculate_initial_ssc_bac(rnd_ic: &[u8], rnd_ifd: &[u8]) -> u64 {
 }
 
 /// Authenticate with Basic Access Control
+///
+/// Returns (KS.ENC, KS.MAC, SSC), or an error if the card stopped answering or rejected us.
 pub fn do_bac_authentication(
     port: &mut Box<impl Smartcard + ?Sized>,
     document_number: &String,
     date_of_birth: &String,
     date_of_expiry: &String,
-) -> (Vec<u8>, Vec<u8>, u64) {
+) -> Result<(Vec<u8>, Vec<u8>, u64), Box<dyn Error>> {
     info!("<d>Starting Basic Access Control</>");
 
     // Get RND.IC by calling GET_CHALLENGE.
     let mut apdu = iso7816::apdu_get_challenge();
-    let (rapdu, _) = apdu.exchange(port, true);
+    let (rapdu, status_code) = apdu.exchange(port, false)?;
+    if status_code != iso7816::StatusCode::Ok as u16 || rapdu.len() < 8 {
+        return Err(Box::new(types::ReadError {
+            additional_text: format!("GET CHALLENGE failed ({:04x}).", status_code),
+        }));
+    }
     // get the first 8 bytes of the response, which is the actual response
     // (rest is SW and checksum)
     let rnd_ic = &rapdu[0..8];
@@ -247,7 +321,15 @@ pub fn do_bac_authentication(
     // Do EXTERNAL_AUTHENTICATION with the key and MAC we calculated.
     let external_auth_data = vec![e_ifd, m_ifd].concat();
     let mut apdu = iso7816::apdu_external_authentication(external_auth_data);
-    let (rapdu, _) = apdu.exchange(port, true);
+    let (rapdu, status_code) = apdu.exchange(port, false)?;
+    if status_code != iso7816::StatusCode::Ok as u16 || rapdu.len() < 40 {
+        return Err(Box::new(types::ReadError {
+            additional_text: format!(
+                "BAC failed ({:04x}), are the document number, date of birth and date of expiry right?",
+                status_code
+            ),
+        }));
+    }
     info!("Successfully authenticated!");
 
     // Calculate session keys
@@ -261,7 +343,7 @@ pub fn do_bac_authentication(
     // Calculate session counter
     let ssc = calculate_initial_ssc_bac(rnd_ic, &rnd_ifd);
 
-    return (ks_enc, ks_mac, ssc);
+    return Ok((ks_enc, ks_mac, ssc));
 }
 
 pub fn do_authentication(
@@ -270,7 +352,7 @@ pub fn do_authentication(
     document_number: &String,
     date_of_birth: &String,
     date_of_expiry: &String,
-) -> (Vec<u8>, Vec<u8>, u64) {
+) -> Result<(Vec<u8>, Vec<u8>, u64), Box<dyn Error>> {
     // TODO: check if reading things without auth is possible, GH#7
     // TODO: make the return type of this an AuthState object,
     // have it state if we need secure comms and what arguments are relevant
@@ -3,6 +3,7 @@ use simplelog::{debug, error, trace};
 use simplelog::{info, warn};
 ///! ISO 7816 APDU handlers (for ICAO 9303 only)
 use std::cmp::min;
+use std::error::Error;
 use strum::{FromRepr, IntoStaticStr};
 
 use crate::helpers;
@@ -13,6 +14,7 @@ use crate::types;
 #[repr(u8)]
 pub enum Command {
     ReadBinary = 0xB0,
+    ReadBinaryOdd = 0xB1,
     SelectFile = 0xA4,
     GetChallenge = 0x84,
     ExternalAuthentication = 0x82,
@@ -68,6 +70,78 @@ pub enum StatusCode {
     Unknown = 0x6F00,
 }
 
+/// Bytes to read per READ BINARY if we don't know the chip's buffer sizes.
+/// This fits in a short response with secure messaging, and is what most readers default to.
+const DEFAULT_READ_CHUNK_LEN: u16 = 0xDF;
+/// Bytes to read per READ BINARY if the chip or reader rejects the length we picked.
+/// Small enough for any chip, even the ones that don't handle the default.
+const FALLBACK_READ_CHUNK_LEN: u16 = 0x80;
+/// Largest response a short Le can ask for.
+const MAX_SHORT_RESPONSE_LEN: usize = 0x100;
+/// Largest response an extended Le can ask for.
+const MAX_EXTENDED_RESPONSE_LEN: usize = 0x10000;
+/// Bytes to read first from ASN.1 files, enough for a three byte tag and a five byte length.
+const TLV_HEADER_READ_LEN: u16 = 8;
+/// Largest offset READ BINARY (B0) can take, beyond it we need READ BINARY (B1).
+const MAX_READ_BINARY_OFFSET: usize = 0x7FFF;
+
+/// APDU sizes the chip can handle, from EF.ATR/INFO.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ApduLimits {
+    /// Whether the chip accepts extended Lc and Le fields
+    pub extended_length: bool,
+    /// Max bytes in a command APDU
+    pub max_command_len: Option<usize>,
+    /// Max bytes in a response APDU
+    pub max_response_len: Option<usize>,
+}
+
+impl ApduLimits {
+    /// Returns how many bytes a secure messaging response takes up for the given amount of data.
+    fn secure_response_len(data_len: usize) -> usize {
+        // DO'87': padding indicator plus the data, padded to the next block with at least one byte
+        let padded_data_len = (data_len / 8 + 1) * 8;
+        let do87_value_len = 1 + padded_data_len;
+        let do87_len_field_len = match do87_value_len {
+            0..=0x7F => 1,
+            0x80..=0xFF => 2,
+            _ => 3,
+        };
+        // DO'99' (status code) and DO'8E' (MAC)
+        return 1 + do87_len_field_len + do87_value_len + 4 + 10;
+    }
+
+    /// Picks how many bytes to read per READ BINARY, so that the response fits the chip's buffer.
+    ///
+    /// Extended lengths are only used if both the chip and the reader support them.
+    pub fn read_chunk_len(&self, secure_comms: bool, reader_extended_length: bool) -> u16 {
+        let max_apdu_response_len = match self.extended_length && reader_extended_length {
+            true => MAX_EXTENDED_RESPONSE_LEN,
+            false => MAX_SHORT_RESPONSE_LEN,
+        };
+        let max_response_len = match self.max_response_len {
+            // - 2 for the status code
+            Some(max_response_len) => {
+                min(max_response_len.saturating_sub(2), max_apdu_response_len)
+            }
+            None => return DEFAULT_READ_CHUNK_LEN,
+        };
+        // Le can't go above 0xFFFF, as we leave out the maximum (which would be encoded as 0).
+        let mut chunk_len = min(max_response_len, 0xFFFF);
+        while secure_comms
+            && chunk_len > 0
+            && Self::secure_response_len(chunk_len) > max_response_len
+        {
+            chunk_len -= 1;
+        }
+        if chunk_len == 0 {
+            warn!("Chip's response buffer seems too small, reading with the default chunk size.");
+            return DEFAULT_READ_CHUNK_LEN;
+        }
+        return chunk_len as u16;
+    }
+}
+
 #[derive(Debug)]
 pub struct ApduCommand {
     pub cla: u8,           // class
@@ -79,23 +153,42 @@ pub struct ApduCommand {
 }
 
 impl ApduCommand {
-    fn get_field_len_vec(field: u16) -> Vec<u8> {
-        let field_bytes = field.to_le_bytes();
-        let field_len: Vec<u8> = match field {
-            0 => vec![],
-            1..256 => vec![field as u8],
-            256.. => vec![0, field_bytes[0], field_bytes[1]],
+    /// Whether Lc or Le don't fit in short fields. ISO/IEC 7816-4 has both extended if either is.
+    fn needs_extended_length(data_len: usize, max_resp_len: u16) -> bool {
+        return data_len > 0xFF || max_resp_len as usize > MAX_SHORT_RESPONSE_LEN;
+    }
+
+    /// Encodes Lc, which is left out if there's no data.
+    fn get_lc_field(data_len: usize, extended_length: bool) -> Vec<u8> {
+        let data_len_bytes = (data_len as u16).to_be_bytes();
+        return match (data_len, extended_length) {
+            (0, _) => vec![],
+            (_, false) => vec![data_len as u8],
+            (_, true) => vec![0x00, data_len_bytes[0], data_len_bytes[1]],
+        };
+    }
+
+    /// Encodes Le, which is left out if no response is expected.
+    ///
+    /// Extended Le starts with 00 unless there's an Lc, and a short Le of 256 is encoded as 00.
+    fn get_le_field(max_resp_len: u16, extended_length: bool, has_lc: bool) -> Vec<u8> {
+        let max_resp_len_bytes = max_resp_len.to_be_bytes();
+        return match (max_resp_len, extended_length, has_lc) {
+            (0, _, _) => vec![],
+            (_, false, _) => vec![max_resp_len as u8],
+            (_, true, false) => vec![0x00, max_resp_len_bytes[0], max_resp_len_bytes[1]],
+            (_, true, true) => vec![max_resp_len_bytes[0], max_resp_len_bytes[1]],
         };
-        return field_len;
     }
 
     /// Serialize the APDU to a byte stream
     pub fn serialize(&self) -> Vec<u8> {
         // https://en.wikipedia.org/wiki/Smart_card_application_protocol_data_unit#APDU_message_command-response_pair
+        let extended_length = Self::needs_extended_length(self.data.len(), self.max_resp_len);
         // Lc: length of data
-        let lc = Self::get_field_len_vec(self.data.len() as u16);
+        let lc = Self::get_lc_field(self.data.len(), extended_length);
         // Le: length of expected response
-        let le = Self::get_field_len_vec(self.max_resp_len);
+        let le = Self::get_le_field(self.max_resp_len, extended_length, !lc.is_empty());
 
         let apdu = vec![
             vec![self.cla, self.ins, self.p1, self.p2],
@@ -123,8 +216,12 @@ impl ApduCommand {
         // We only ever use CLA=0x00 so we could hardcode this to 0x0C, but I want to be thorough.
         let cla = self.cla | 0x0C;
 
-        // Le: length of expected response
-        let base_le = Self::get_field_len_vec(self.max_resp_len);
+        // Le: length of expected response, one byte unless it doesn't fit
+        let base_le = Self::get_le_field(
+            self.max_resp_len,
+            self.max_resp_len as usize > MAX_SHORT_RESPONSE_LEN,
+            true,
+        );
         let cmd = vec![cla, self.ins, self.p1, self.p2];
         let padded_cmd = icao9303::padding_method_2_pad(&cmd);
         debug!("padded_cmd: {:02x?}", padded_cmd);
@@ -141,27 +238,27 @@ impl ApduCommand {
             // ISO 7816-4-2020+A1-2023: "When bit b1 of INS is set to 1 (odd INS code, see 5.5), the unsecured data
             // fields are encoded in ber-tlv and SM tags 'B2', 'B3', '84' and '85' shall be used for their encapsulation;
             // unless the use of tags '80', '81', '86' and '87' is specified at application level."
-            // Only very few commands in ISO 7816-4 have odd INS numbers.
-            // In this context we only use even commands so far, so having only DO'87' may be enough.
+            // Only very few commands in ISO 7816-4 have odd INS numbers, like READ BINARY (B1).
+            let encrypted_data = icao9303::tdes_enc(ks_enc, &padded_data);
+            debug!("encrypted_data: {:02x?}", encrypted_data);
 
             // If instruction is an even number
-            if self.ins % 2 == 0 {
-                let encrypted_data = icao9303::tdes_enc(ks_enc, &padded_data);
-                debug!("encrypted_data: {:02x?}", encrypted_data);
+            let do_tlv = if self.ins % 2 == 0 {
                 // Tag is 0x87, "Padding-content indicator byte followed by cryptogram".
                 let tag = ber::Tag::try_from(0x87).unwrap();
                 // Value in DO'87' is data prepended with the Padding-content indicator byte.
                 // 0x01 is padding method 2 according to ISO 7816-4-2020+A1-2023, Table 53.
                 let value = vec![[0x01].as_slice(), &encrypted_data].concat();
-
-                let do_87_tlv = ber::Tlv::new(tag, ber::Value::Primitive(value)).unwrap();
-                debug!("do_87_tlv: {:02x?}", do_87_tlv);
-                secure_data.extend_from_slice(&do_87_tlv.to_vec());
+                ber::Tlv::new(tag, ber::Value::Primitive(value)).unwrap()
             // If instruction is an odd number
             } else {
                 // Tag is 0x85, "Cryptogram (plain value encoded in ber-tlv, but not including SM DOs)".
-                panic!("DO'85' is not implemented.");
-            }
+                // Data of odd INS commands is already BER-TLV, and there's no Padding-content indicator byte.
+                let tag = ber::Tag::try_from(0x85).unwrap();
+                ber::Tlv::new(tag, ber::Value::Primitive(encrypted_data)).unwrap()
+            };
+            debug!("do_tlv: {:02x?}", do_tlv);
+            secure_data.extend_from_slice(&do_tlv.to_vec());
         }
 
         if self.max_resp_len != 0 {
@@ -202,11 +299,18 @@ impl ApduCommand {
         secure_data.extend_from_slice(&do_8e_tlv.to_vec());
         debug!("final secure_data: {:02x?}", secure_data);
 
+        // The secured response is larger than the data we asked for, so we may need an extended Le for it.
+        let extended_length = secure_data.len() > 0xFF
+            || ApduLimits::secure_response_len(self.max_resp_len as usize) > MAX_SHORT_RESPONSE_LEN;
+
         // Lc: length of data
-        let lc = Self::get_field_len_vec(secure_data.len() as u16);
+        let lc = Self::get_lc_field(secure_data.len(), extended_length);
 
-        // Outer Le is set to 0x00 to allow the full frame
-        let le = vec![0x00];
+        // Outer Le is set to 0x00 (or 0x0000) to allow the full frame
+        let le = match extended_length {
+            true => vec![0x00, 0x00],
+            false => vec![0x00],
+        };
 
         let apdu = vec![cmd, lc, secure_data, le].concat();
         return apdu;
@@ -214,20 +318,20 @@ impl ApduCommand {
 
     /// Send APDU to the given smartcard
     ///
-    /// Returns (RAPDU, status code)
+    /// Returns (RAPDU, status code), or an error if the card stopped answering.
     pub fn exchange(
         &mut self,
         smartcard: &mut Box<impl Smartcard + ?Sized>,
         assert_on_status: bool,
-    ) -> (Vec<u8>, u16) {
+    ) -> Result<(Vec<u8>, u16), Box<dyn Error>> {
         let (rapdu, status_code) =
-            self.secure_exchange(smartcard, assert_on_status, false, &mut 0, &vec![], &vec![]);
-        return (rapdu, status_code);
+            self.secure_exchange(smartcard, assert_on_status, false, &mut 0, &vec![], &vec![])?;
+        return Ok((rapdu, status_code));
     }
 
     /// Send APDU to the given smartcard using secure communications
     ///
-    /// Returns (RAPDU, status code)
+    /// Returns (RAPDU, status code), or an error if the card stopped answering.
     pub fn secure_exchange(
         &mut self,
         smartcard: &mut Box<impl Smartcard + ?Sized>,
@@ -236,7 +340,7 @@ impl ApduCommand {
         ssc: &mut u64,
         ks_enc: &Vec<u8>,
         ks_mac: &Vec<u8>,
-    ) -> (Vec<u8>, u16) {
+    ) -> Result<(Vec<u8>, u16), Box<dyn Error>> {
         let mut done_exchanging = false;
         let mut rapdu_data: Vec<u8> = vec![];
         let mut status_code_bytes: Vec<u8> = vec![];
@@ -248,12 +352,17 @@ impl ApduCommand {
                 self.serialize()
             };
 
-            rapdu_data = smartcard.exchange_apdu(&apdu_bytes).unwrap();
+            // A card taken off the reader doesn't answer at all.
+            rapdu_data = match smartcard.exchange_apdu(&apdu_bytes) {
+                Some(rapdu_data) if rapdu_data.len() >= 2 => rapdu_data,
+                _ => return Err(Box::new(types::CardLostError {})),
+            };
             status_code_bytes = get_status_code_bytes(&rapdu_data);
 
             // - 2 bytes for status code
             if secure_comms {
-                match parse_secure_rapdu(&rapdu_data[..rapdu_data.len() - 2], ssc, ks_enc, ks_mac) {
+                match parse_secure_rapdu(&rapdu_data[..rapdu_data.len() - 2], ssc, ks_enc, ks_mac)?
+                {
                     Some(data) => {
                         rapdu_data = data;
                     }
@@ -287,57 +396,117 @@ impl ApduCommand {
             assert!(status_code == StatusCode::Ok as u16);
         }
 
-        return (rapdu_data, status_code);
+        return Ok((rapdu_data, status_code));
     }
 }
 
-pub fn select_and_read_file(
+/// Selects a file by its file ID, returns whether it was found.
+fn select_file(
     smartcard: &mut Box<impl Smartcard + ?Sized>,
     dg_info: &types::DataGroup,
     secure_comms: bool,
     ssc: &mut u64,
     ks_enc: &Vec<u8>,
     ks_mac: &Vec<u8>,
-) -> Option<Vec<u8>> {
+) -> Result<bool, Box<dyn Error>> {
     info!("<d>Selecting {} ({})</>", dg_info.name, dg_info.description);
     let mut apdu = apdu_select_file_by_ef(dg_info.file_id);
     let (_, status_code) =
-        apdu.secure_exchange(smartcard, false, secure_comms, ssc, ks_enc, ks_mac);
+        apdu.secure_exchange(smartcard, false, secure_comms, ssc, ks_enc, ks_mac)?;
 
     if status_code != StatusCode::Ok as u16 {
         warn!("{} not found (this is probably fine).", dg_info.name);
-        return None;
+        return Ok(false);
+    }
+    return Ok(true);
+}
+
+pub fn select_and_read_file(
+    smartcard: &mut Box<impl Smartcard + ?Sized>,
+    dg_info: &types::DataGroup,
+    apdu_limits: &ApduLimits,
+    secure_comms: bool,
+    ssc: &mut u64,
+    ks_enc: &Vec<u8>,
+    ks_mac: &Vec<u8>,
+) -> Result<Option<Vec<u8>>, Box<dyn Error>> {
+    // Reading with the SFI selects the file implicitly, which saves us a SELECT.
+    let mut sfi = dg_info.sfi;
+    if sfi.is_none() && !select_file(smartcard, dg_info, secure_comms, ssc, ks_enc, ks_mac)? {
+        return Ok(None);
     }
 
     info!("<d>Reading {} ({})</>", dg_info.name, dg_info.description);
     let mut total_data: Vec<u8> = vec![];
-    let mut bytes_to_read = 0x05;
-    let mut file_len: u16 = 0;
+    let mut read_chunk_len =
+        apdu_limits.read_chunk_len(secure_comms, smartcard.supports_extended_length());
+    // Unfortunately, ICAO 9303 does not allow us to read file sizes.
+    // For ASN.1 files, we read the TLV header first and get the size from it.
+    // Other files are read until the chip tells us that we reached the end.
+    let mut file_len: Option<usize> = None;
+    let mut bytes_to_read = match dg_info.is_asn1 {
+        true => TLV_HEADER_READ_LEN,
+        false => read_chunk_len,
+    };
     while bytes_to_read > 0 {
-        let mut apdu = apdu_read_binary(total_data.len() as u16, bytes_to_read);
-        let (apdu_data, status_code) =
-            apdu.secure_exchange(smartcard, false, secure_comms, ssc, ks_enc, ks_mac);
+        let mut apdu = match (total_data.len(), sfi) {
+            (0, Some(sfi)) => apdu_read_binary_by_sfi(sfi, 0, bytes_to_read),
+            (0..=MAX_READ_BINARY_OFFSET, _) => {
+                apdu_read_binary(total_data.len() as u16, bytes_to_read)
+            }
+            _ => apdu_read_binary_with_offset_do(total_data.len() as u32, bytes_to_read),
+        };
+        let (mut apdu_data, status_code) =
+            apdu.secure_exchange(smartcard, false, secure_comms, ssc, ks_enc, ks_mac)?;
         let status_code_bytes = status_code.to_be_bytes();
+        let read_ok = status_code_bytes[0] == 0x90 || status_code == StatusCode::FileEof as u16;
+
+        // Not every chip supports reading by SFI, so fall back to selecting the file.
+        if sfi.is_some() && !read_ok && status_code_bytes[0] != 0x61 {
+            debug!(
+                "Reading {} by SFI failed ({:04x}), selecting it instead.",
+                dg_info.name, status_code
+            );
+            sfi = None;
+            if !select_file(smartcard, dg_info, secure_comms, ssc, ks_enc, ks_mac)? {
+                return Ok(None);
+            }
+            continue;
+        }
+        // Once read by SFI, the file is selected and we can use regular reads.
+        sfi = None;
 
-        // Unfortunately, ICAO 9303 does not allow us to read file sizes.
-        // We must, therefore, read the ASN.1 header to get the size.
-        // I'd love to replace this with a better solution if I find one.
-        if total_data.is_empty() && status_code_bytes[0] == 0x90 {
-            // TODO: this does not account for non-ASN1 files. We can use .is_asn1.
-            let (field_len, asn1_len) = helpers::asn1_parse_len(apdu_data[1..].to_vec());
-            // TODO: rethink this u16.
-            // We should account for u32 even tho its unlikely.
-            // offset by 1 as we're skipping the initial tag.
-            file_len = (1u32 + field_len as u32 + asn1_len) as u16;
-
-            if file_len > 5_000 {
-                info!(
+        // Some chips or readers don't handle the chunk size we picked, so retry with smaller ones.
+        if status_code == StatusCode::WrongLength as u16 && read_chunk_len > FALLBACK_READ_CHUNK_LEN
+        {
+            warn!(
+                "<d>Reading {}b chunks failed, reading {}b chunks instead.</>",
+                read_chunk_len, FALLBACK_READ_CHUNK_LEN
+            );
+            read_chunk_len = FALLBACK_READ_CHUNK_LEN;
+            bytes_to_read = min(bytes_to_read, read_chunk_len);
+            continue;
+        }
+        if apdu.ins == Command::ReadBinaryOdd as u8 && read_ok {
+            apdu_data = unwrap_discretionary_data(apdu_data);
+        }
+
+        if total_data.is_empty() && dg_info.is_asn1 && status_code_bytes[0] == 0x90 {
+            file_len = helpers::asn1_parse_tlv_len(&apdu_data);
+            match file_len {
+                Some(file_len) if file_len > 5_000 => info!(
                     "<d>{} seems quite large ({}b), this may take a bit.</>",
                     dg_info.name, file_len
-                );
+                ),
+                Some(_) => {}
+                None => warn!(
+                    "<d>Couldn't parse the TLV header of {}, reading until the end of the file.</>",
+                    dg_info.name
+                ),
             }
         }
 
+        let read_len = total_data.len() + apdu_data.len();
         debug!(
             "Reading file, file_len: {:?} total_data.len(): {:?} apdu_data.len(): {:?}",
             file_len,
@@ -351,17 +520,38 @@ pub fn select_and_read_file(
         // and using SW2 (number of data bytes still available) as short Le field.
         if status_code_bytes[0] == 0x61 {
             bytes_to_read = u16::from(status_code_bytes[1]);
-        } else if (((total_data.len() + apdu_data.len()) as u16) < file_len)
-            && (status_code_bytes[0] == 0x90)
-        {
-            bytes_to_read = min(0x80, file_len - (total_data.len() + apdu_data.len()) as u16);
-        } else {
+        } else if status_code == StatusCode::FileEof as u16 {
+            // The file ended before we read all the bytes we asked for.
             bytes_to_read = 0;
+            match file_len {
+                Some(file_len) if read_len < file_len => warn!(
+                    "{} ended after {}b, but its header says it's {}b.",
+                    dg_info.name, read_len, file_len
+                ),
+                _ => {}
+            }
+        } else if status_code_bytes[0] == 0x90 {
+            bytes_to_read = match file_len {
+                Some(file_len) => {
+                    min(read_chunk_len as usize, file_len.saturating_sub(read_len)) as u16
+                }
+                // Without a known length, getting less than we asked for means we reached the end.
+                None if apdu_data.len() < apdu.max_resp_len as usize => 0,
+                None => read_chunk_len,
+            };
+        } else if total_data.is_empty() || file_len.is_some() {
             // if the read failed at some point, return None
             // TODO: this'd be a good spot to report status code text
-            if status_code_bytes[0] != 0x90 {
-                return None;
-            }
+            return Ok(None);
+        } else {
+            // Some chips reply with an error (like 6B00) instead of 6282 when reading past the end.
+            debug!(
+                "Reading {} past {}b failed ({:04x}), assuming that's the end of it.",
+                dg_info.name,
+                total_data.len(),
+                status_code
+            );
+            bytes_to_read = 0;
         }
 
         total_data.extend(apdu_data);
@@ -369,23 +559,23 @@ pub fn select_and_read_file(
     debug!("Read file ({:?}b): {:?}", total_data.len(), total_data);
     // only return data if it's not empty.
     return if total_data.is_empty() {
-        None
+        Ok(None)
     } else {
-        Some(total_data)
+        Ok(Some(total_data))
     };
 }
 
 /// Parse a secure Response APDU
 ///
 /// Currently supports DO'99', '87' and '8E'
-/// Returns the decrypted data from DO'87'
+/// Returns the decrypted data from DO'87', or an error if the response is garbled or its MAC doesn't match.
 pub fn parse_secure_rapdu(
     rapdu: &[u8],
     ssc: &mut u64,
     ks_enc: &Vec<u8>,
     ks_mac: &Vec<u8>,
-) -> Option<Vec<u8>> {
-    const SIGNATURE_CHECK_CONCAT_ORDER: [u16; 2] = [0x87, 0x99];
+) -> Result<Option<Vec<u8>>, types::SecureMessagingError> {
+    const SIGNATURE_CHECK_CONCAT_ORDER: [u16; 3] = [0x85, 0x87, 0x99];
     // Increment SSC when we receive a secure RAPDU
     *ssc += 1;
     debug!("post-bump ssc: {:02x?}", ssc);
@@ -395,11 +585,7 @@ pub fn parse_secure_rapdu(
     let rapdu_tlvs = helpers::sort_tlvs_by_tag(&parsed_rapdu);
     debug!("rapdu_tlvs: {:02x?}", rapdu_tlvs);
 
-    if rapdu_tlvs.contains_key(&0x85) {
-        panic!("DO'85' is not implemented.");
-    }
-
-    // Concat SSC + [DO'87'] + DO'99' + padding, to compare against DO'8E'
+    // Concat SSC + [DO'85' or DO'87'] + DO'99' + padding, to compare against DO'8E'
     let mut signature_check_data: Vec<u8> = ssc.to_be_bytes().to_vec();
     for tlv_tag_id in SIGNATURE_CHECK_CONCAT_ORDER {
         match rapdu_tlvs.get(&tlv_tag_id) {
@@ -417,29 +603,59 @@ pub fn parse_secure_rapdu(
     debug!("signature_check_mac: {:02x?}", signature_check_mac);
 
     // Extract the value of DO'8E' and compare to the MAC we calculated.
-    let do_8e_tlv = rapdu_tlvs.get(&0x8E)?;
+    let do_8e_tlv = match rapdu_tlvs.get(&0x8E) {
+        Some(do_8e_tlv) => do_8e_tlv,
+        None => return Ok(None),
+    };
     let do_8e_value = helpers::get_tlv_value_bytes(do_8e_tlv.to_owned());
-    assert!(signature_check_mac == do_8e_value);
-
-    // Extract the value of DO'87' and return the encrypted data.
-    // This assumes we don't have a DO'85' and that we always have DO'87'.
-    if rapdu_tlvs.contains_key(&0x87) {
-        let do_87_tlv = rapdu_tlvs.get(&0x87).unwrap();
-        let mut do_87_value = helpers::get_tlv_value_bytes(do_87_tlv.to_owned());
-        // We skip first byte due to it being the "Padding-content indicator byte".
-        // ICAO 9303 only allows one value, so we don't need to think much about it.
-        // Still, an assert is here.
-        assert!(do_87_value[0] == 0x01);
-        do_87_value = do_87_value[1..].to_vec();
-        debug!("do_87_value: {:02x?}", do_87_value);
-        let decrypted_data = icao9303::tdes_dec(ks_enc, &do_87_value);
-        debug!("decrypted_data: {:02x?}", decrypted_data);
-        let decrypted_unpadded_data = icao9303::padding_method_2_unpad(&decrypted_data);
-        debug!("decrypted_unpadded_data: {:02x?}", decrypted_unpadded_data);
-        return Some(decrypted_unpadded_data);
+    if signature_check_mac != do_8e_value {
+        return Err(types::SecureMessagingError {
+            additional_text: "MAC in DO'8E' doesn't match.".to_string(),
+        });
     }
 
-    return None;
+    // Extract the value of DO'87' or DO'85' and return the decrypted data.
+    // DO'85' is used for responses to odd INS commands, and holds BER-TLV encoded data (like DO'53').
+    let encrypted_data = match (rapdu_tlvs.get(&0x87), rapdu_tlvs.get(&0x85)) {
+        (Some(do_87_tlv), _) => {
+            let do_87_value = helpers::get_tlv_value_bytes(do_87_tlv.to_owned());
+            // We skip first byte due to it being the "Padding-content indicator byte".
+            // ICAO 9303 only allows one value, so we don't need to think much about it.
+            if do_87_value.first() != Some(&0x01) {
+                return Err(types::SecureMessagingError {
+                    additional_text: format!(
+                        "DO'87' has an unsupported padding-content indicator: {:02x?}",
+                        do_87_value.first()
+                    ),
+                });
+            }
+            do_87_value[1..].to_vec()
+        }
+        // DO'85' has no Padding-content indicator byte.
+        (None, Some(do_85_tlv)) => helpers::get_tlv_value_bytes(do_85_tlv.to_owned()),
+        (None, None) => return Ok(None),
+    };
+    debug!("encrypted_data: {:02x?}", encrypted_data);
+    if encrypted_data.is_empty() || encrypted_data.len() % 8 != 0 {
+        return Err(types::SecureMessagingError {
+            additional_text: format!(
+                "Encrypted data isn't a whole number of blocks ({}b).",
+                encrypted_data.len()
+            ),
+        });
+    }
+    let decrypted_data = icao9303::tdes_dec(ks_enc, &encrypted_data);
+    debug!("decrypted_data: {:02x?}", decrypted_data);
+    let decrypted_unpadded_data = match icao9303::padding_method_2_unpad(&decrypted_data) {
+        Some(decrypted_unpadded_data) => decrypted_unpadded_data,
+        None => {
+            return Err(types::SecureMessagingError {
+                additional_text: "Decrypted data isn't padded correctly.".to_string(),
+            });
+        }
+    };
+    debug!("decrypted_unpadded_data: {:02x?}", decrypted_unpadded_data);
+    return Ok(Some(decrypted_unpadded_data));
 }
 
 pub const P1_SELECT_BY_EF: u8 = 0x02;
@@ -501,6 +717,55 @@ pub fn apdu_read_binary(offset: u16, bytes_to_read: u16) -> ApduCommand {
     };
 }
 
+/// READ BINARY of the file with the given Short File Identifier, which also selects it.
+///
+/// The offset is limited to P2, so this is only useful for the first read.
+pub fn apdu_read_binary_by_sfi(sfi: u8, offset: u8, bytes_to_read: u16) -> ApduCommand {
+    return ApduCommand {
+        cla: 0,
+        ins: Command::ReadBinary as u8,
+        // b8 set means b5-b1 hold the SFI
+        p1: 0x80 | sfi,
+        p2: offset,
+        data: vec![],
+        max_resp_len: bytes_to_read,
+    };
+}
+
+/// READ BINARY with the offset in DO'54', for offsets that don't fit into P1 and P2.
+///
+/// The response is wrapped in DO'53', see unwrap_discretionary_data.
+pub fn apdu_read_binary_with_offset_do(offset: u32, bytes_to_read: u16) -> ApduCommand {
+    let offset_bytes = offset.to_be_bytes();
+    // Offset is encoded in as few bytes as possible.
+    let first_offset_byte = offset_bytes.iter().position(|byte| *byte != 0).unwrap_or(3);
+    let offset_do = ber::Tlv::new(
+        ber::Tag::try_from(0x54).unwrap(),
+        ber::Value::Primitive(offset_bytes[first_offset_byte..].to_vec()),
+    )
+    .unwrap();
+    return ApduCommand {
+        cla: 0,
+        ins: Command::ReadBinaryOdd as u8,
+        // 0000 is the currently selected EF
+        p1: 0x00,
+        p2: 0x00,
+        data: offset_do.to_vec(),
+        max_resp_len: bytes_to_read,
+    };
+}
+
+/// Returns the value of the DO'53' that READ BINARY (B1) wraps data in, or the data as-is if there's none.
+pub fn unwrap_discretionary_data(data: Vec<u8>) -> Vec<u8> {
+    match ber::Tlv::parse(&data).0 {
+        Ok(tlv) if helpers::get_tlv_tag(&tlv) == 0x53 => match tlv.value() {
+            ber::Value::Primitive(value) => return value.clone(),
+            ber::Value::Constructed(_) => return data,
+        },
+        _ => return data,
+    }
+}
+
 pub fn apdu_get_challenge() -> ApduCommand {
     return ApduCommand {
         cla: 0,
@@ -523,3 +788,101 @@ pub fn apdu_external_authentication(data: Vec<u8>) -> ApduCommand {
         max_resp_len: 40,
     };
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn apdu_serialization() {
+        // Short Le, with 256 encoded as 00
+        assert_eq!(
+            apdu_read_binary(0x0102, 0xDF).serialize(),
+            [0x00, 0xB0, 0x01, 0x02, 0xDF]
+        );
+        assert_eq!(
+            apdu_read_binary(0, 0x100).serialize(),
+            [0x00, 0xB0, 0x00, 0x00, 0x00]
+        );
+        // SFI in P1
+        assert_eq!(
+            apdu_read_binary_by_sfi(0x1E, 0, 5).serialize(),
+            [0x00, 0xB0, 0x9E, 0x00, 0x05]
+        );
+        // Extended Le without data
+        assert_eq!(
+            apdu_read_binary(0, 0x0700).serialize(),
+            [0x00, 0xB0, 0x00, 0x00, 0x00, 0x07, 0x00]
+        );
+        // Offset DO with an extended Le, which then has no leading 00
+        assert_eq!(
+            apdu_read_binary_with_offset_do(0x012345, 0x0400).serialize(),
+            [0x00, 0xB1, 0x00, 0x00, 0x00, 0x00, 0x05, 0x54, 0x03, 0x01, 0x23, 0x45, 0x04, 0x00]
+        );
+        assert_eq!(
+            unwrap_discretionary_data(vec![0x53, 0x02, 0xAB, 0xCD]),
+            [0xAB, 0xCD]
+        );
+    }
+
+    #[test]
+    fn odd_ins_secure_messaging() {
+        // Session keys and SSC from ICAO 9303 p11, Appendix D.4
+        let ks_enc = vec![
+            0x97, 0x9E, 0xC1, 0x3B, 0x1C, 0xBF, 0xE9, 0xDC, 0xD0, 0x1A, 0xB0, 0xFE, 0xD3, 0x07,
+            0xEA, 0xE5,
+        ];
+        let ks_mac = vec![
+            0xF1, 0xCB, 0x1F, 0x1F, 0xB5, 0xAD, 0xF2, 0x08, 0x80, 0x6B, 0x89, 0xDC, 0x57, 0x9D,
+            0xC1, 0xF8,
+        ];
+        let mut ssc: u64 = 0x887022120C06C226;
+
+        // Command data goes into DO'85' without a Padding-content indicator byte
+        let secure_apdu = apdu_read_binary_with_offset_do(0x8000, 0x10)
+            .bac_secure_serialize(&mut ssc, &ks_enc, &ks_mac);
+        assert_eq!(secure_apdu[..4], [0x0C, 0xB1, 0x00, 0x00]);
+        assert_eq!(secure_apdu[5..7], [0x85, 0x08]);
+        let decrypted_data = icao9303::tdes_dec(&ks_enc, &secure_apdu[7..15]);
+        assert_eq!(
+            icao9303::padding_method_2_unpad(&decrypted_data),
+            Some(vec![0x54, 0x02, 0x80, 0x00])
+        );
+
+        // Response data comes back in DO'85' too
+        let plain_data = vec![0x53, 0x02, 0xAB, 0xCD];
+        let encrypted_data =
+            icao9303::tdes_enc(&ks_enc, &icao9303::padding_method_2_pad(&plain_data));
+        let do_85_and_99 = [
+            &[0x85, 0x08][..],
+            &encrypted_data,
+            &[0x99, 0x02, 0x90, 0x00],
+        ]
+        .concat();
+        let mac = icao9303::retail_mac(
+            &ks_mac,
+            &icao9303::padding_method_2_pad(
+                &[&(ssc + 1).to_be_bytes()[..], &do_85_and_99].concat(),
+            ),
+        );
+        let rapdu = [&do_85_and_99[..], &[0x8E, 0x08], &mac].concat();
+        assert_eq!(
+            parse_secure_rapdu(&rapdu, &mut ssc, &ks_enc, &ks_mac).unwrap(),
+            Some(plain_data)
+        );
+
+        // Garbled responses are reported instead of panicking
+        let mut garbled_rapdu = rapdu.clone();
+        *garbled_rapdu.last_mut().unwrap() ^= 0xFF;
+        assert!(parse_secure_rapdu(&garbled_rapdu, &mut ssc, &ks_enc, &ks_mac).is_err());
+        let empty_do_87_and_99 = [0x87, 0x00, 0x99, 0x02, 0x90, 0x00];
+        let mac = icao9303::retail_mac(
+            &ks_mac,
+            &icao9303::padding_method_2_pad(
+                &[&(ssc + 1).to_be_bytes()[..], &empty_do_87_and_99].concat(),
+            ),
+        );
+        let rapdu = [&empty_do_87_and_99[..], &[0x8E, 0x08], &mac].concat();
+        assert!(parse_secure_rapdu(&rapdu, &mut ssc, &ks_enc, &ks_mac).is_err());
+    }
+}
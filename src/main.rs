@@ -2,38 +2,116 @@ mod dg_parsers;
 mod helpers;
 mod icao9303;
 mod iso7816;
+#[cfg(feature = "preview")]
+mod preview;
 #[cfg(feature = "proxmark")]
 mod proxmark;
 mod smartcard_abstractions;
+#[cfg(feature = "vds")]
+mod trust_store;
 mod types;
 
-use clap::Parser;
-use simplelog::{info, warn, CombinedLogger, TermLogger};
+use clap::{Args, Parser, Subcommand};
+use dg_parsers::helpers as dg_helpers;
+use simplelog::{error, info, warn, CombinedLogger, TermLogger};
 use smartcard_abstractions::ReaderInterface;
-use std::path::PathBuf;
+use std::error::Error;
+use std::io::{self, BufRead};
+use std::path::{Path, PathBuf};
 use types::DataGroupEnum;
 
 #[derive(Parser, Debug)]
 #[command(version, about, long_about = None)]
 struct CliArgs {
-    /// Dump files? (path can be optionally supplied, defaults to current directory)
-    #[arg(long = "dump", value_name = "PATH", default_missing_value = ".", value_parser = clap::value_parser!(PathBuf), num_args = 0..=1)]
-    dump_path: Option<PathBuf>,
+    #[command(subcommand)]
+    command: CliCommand,
 
-    /// Path of the reader to use.
-    #[arg(short = 'p', long, value_name = "PATH")]
-    reader: Option<String>,
+    /// How to preview the DG2 portrait in the terminal (auto/kitty/sixel/blocks/off)
+    #[cfg(feature = "preview")]
+    #[arg(long = "preview", value_name = "PROTOCOL", ignore_case = true, global = true, default_value_t = preview::PreviewProtocol::Auto)]
+    preview_protocol: preview::PreviewProtocol,
 
-    /// Reader backend interface to use.
-    #[arg(short = 'i', long, value_name = "proxmark/pcsc", ignore_case = true, default_value_t = ReaderInterface::PCSC)]
-    backend: ReaderInterface,
+    /// Log level (trace/debug/info/warn/error)
+    #[arg(long = "level", ignore_case = true, global = true, default_value_t = simplelog::LevelFilter::Info)]
+    log_level: simplelog::LevelFilter,
+}
+
+#[derive(Subcommand, Debug)]
+enum CliCommand {
+    /// Read a document and print its contents
+    Read {
+        /// Dump files? (path can be optionally supplied, defaults to current directory)
+        #[arg(long = "dump", value_name = "PATH", default_missing_value = ".", value_parser = clap::value_parser!(PathBuf), num_args = 0..=1)]
+        dump_path: Option<PathBuf>,
+
+        #[command(flatten)]
+        read_args: ReadArgs,
+    },
+    /// Read a document and dump all of its files (same as read --dump)
+    Dump {
+        /// Directory to dump the files to
+        #[arg(value_name = "PATH", default_value = ".")]
+        dump_path: PathBuf,
+
+        #[command(flatten)]
+        read_args: ReadArgs,
+    },
+    /// Wait for documents and read each one as it's placed on the reader, asking for its MRZ on stdin
+    Watch {
+        /// Directory to dump the files of each document to
+        #[arg(value_name = "PATH", default_value = ".")]
+        dump_path: PathBuf,
+
+        #[command(flatten)]
+        read_options: ReadOptions,
+    },
+    /// List the readers available through all backends, or the given one
+    ListReaders {
+        /// Reader backend interface to list the readers of.
+        #[arg(short = 'i', long, value_name = "proxmark/pcsc", ignore_case = true)]
+        backend: Option<ReaderInterface>,
+    },
+    /// Parse and print the files of a dump directory
+    Inspect {
+        /// Directory with the dumped files
+        #[arg(value_name = "PATH")]
+        dump_path: PathBuf,
+    },
+    /// Check the files of a dump directory against the hashes in its EF.SOD
+    Verify {
+        /// Directory with the dumped files
+        #[arg(value_name = "PATH")]
+        dump_path: PathBuf,
+    },
+    /// Print an MRZ and validate its check digits
+    MrzCheck {
+        /// Full MRZ of the document, one argument per line
+        #[arg(value_name = "LINE", num_args = 2..=3, required = true)]
+        mrz: Vec<String>,
+    },
+    /// Decode a Visible Digital Seal from a file with the raw barcode contents
+    Vds {
+        /// File with the raw barcode contents
+        #[arg(value_name = "PATH")]
+        vds_path: PathBuf,
+
+        /// Directory of signer certificates (DER or PEM) to verify signatures against
+        #[arg(long = "trust-store", value_name = "PATH", value_parser = clap::value_parser!(PathBuf))]
+        trust_store_path: Option<PathBuf>,
+    },
+}
+
+#[derive(Args, Debug)]
+struct ReadArgs {
+    #[command(flatten)]
+    read_options: ReadOptions,
 
     /// Date of birth, YYMMDD (Requires DoE and Doc Number, mutually exclusive with CAN)
     #[arg(
         short = 'b',
         long = "dob",
         value_name = "YYMMDD",
-        required_unless_present = "card_access_number"
+        required_unless_present_any = ["card_access_number", "mrz"]
     )]
     date_of_birth: Option<String>,
 
@@ -42,7 +120,7 @@ struct CliArgs {
         short = 'e',
         long = "doe",
         value_name = "YYMMDD",
-        required_unless_present = "card_access_number"
+        required_unless_present_any = ["card_access_number", "mrz"]
     )]
     date_of_expiry: Option<String>,
 
@@ -50,17 +128,85 @@ struct CliArgs {
     #[arg(
         short = 'n',
         long = "num",
-        required_unless_present = "card_access_number"
+        required_unless_present_any = ["card_access_number", "mrz"]
     )]
     document_number: Option<String>,
 
     /// Card Access Number (PACE-only, mutually exclusive with DoB, DoE and Doc Number)
-    #[arg(short = 'c', long = "can", required_unless_present_any=["date_of_birth", "date_of_expiry", "document_number"])]
+    #[arg(short = 'c', long = "can", required_unless_present_any=["date_of_birth", "date_of_expiry", "document_number", "mrz"])]
     card_access_number: Option<String>,
 
-    /// Log level (trace/debug/info/warn/error)
-    #[arg(long = "level", ignore_case = true, default_value_t = simplelog::LevelFilter::Info)]
-    log_level: simplelog::LevelFilter,
+    /// Full MRZ of the document, one argument per line (Replaces DoB, DoE and Doc Number)
+    #[arg(short = 'm', long = "mrz", value_name = "LINE", num_args = 2..=3, conflicts_with_all = ["date_of_birth", "date_of_expiry", "document_number"])]
+    mrz: Option<Vec<String>>,
+}
+
+/// Which reader to use and what to read from the documents on it.
+#[derive(Args, Debug)]
+struct ReadOptions {
+    /// Only read these data groups, comma separated (like DG1,SOD)
+    #[arg(long = "only", value_name = "DGS", value_delimiter = ',', value_parser = types::DataGroupSelection::parse_name)]
+    only_data_groups: Option<Vec<&'static str>>,
+
+    /// Don't read these data groups, comma separated (like DG2,DG3)
+    #[arg(long = "skip", value_name = "DGS", value_delimiter = ',', value_parser = types::DataGroupSelection::parse_name)]
+    skip_data_groups: Vec<&'static str>,
+
+    /// Path of the reader to use.
+    #[arg(short = 'p', long, value_name = "PATH")]
+    reader: Option<String>,
+
+    /// Reader backend interface to use.
+    #[arg(short = 'i', long, value_name = "proxmark/pcsc", ignore_case = true, default_value_t = ReaderInterface::PCSC)]
+    backend: ReaderInterface,
+
+    /// Use extended length APDUs if the document supports them (not every PC/SC reader does)
+    #[arg(long)]
+    extended_length: bool,
+}
+
+/// What we authenticate to a document with, taken from the arguments or an MRZ.
+struct Credentials {
+    printed_mrz: Option<types::MRZ>,
+    document_number: Option<String>,
+    date_of_birth: Option<String>,
+    date_of_expiry: Option<String>,
+    card_access_number: Option<String>,
+}
+
+impl Credentials {
+    fn from_mrz(mrz_obj: types::MRZ) -> Credentials {
+        return Credentials {
+            // BAC uses the document number with its filler characters, so pad it back to 9 characters.
+            document_number: Some(format!("{:<<9}", mrz_obj.document_number())),
+            date_of_birth: Some(mrz_obj.date_of_birth().to_string()),
+            date_of_expiry: Some(mrz_obj.date_of_expiry().to_string()),
+            card_access_number: None,
+            printed_mrz: Some(mrz_obj),
+        };
+    }
+}
+
+#[cfg(feature = "vds")]
+fn decode_vds(vds_path: &Path, trust_store_path: &Option<PathBuf>) {
+    let vds_data = std::fs::read(vds_path).expect("Couldn't read the given VDS file.");
+    let seal = types::VisibleDigitalSeal::deserialize(&vds_data)
+        .expect("Couldn't decode the given file as a VDS.");
+    seal.fancy_print();
+
+    let trust_store_path = match trust_store_path {
+        Some(path) => path,
+        None => {
+            warn!("No trust store was given (--trust-store), not verifying the VDS signature.");
+            return;
+        }
+    };
+    let trust_store =
+        trust_store::TrustStore::load(trust_store_path).expect("Couldn't load the trust store.");
+    match seal.verify_signature(&trust_store) {
+        Ok(()) => info!("<green>VDS signature is valid.</>"),
+        Err(err) => error!("<red>{}</>", err),
+    }
 }
 
 fn main() {
@@ -74,15 +220,322 @@ fn main() {
     )])
     .unwrap();
 
-    let filename_distinguisher = match args.document_number.as_ref() {
-        Some(document_number) => document_number,
-        None => &helpers::unix_time().to_string(),
+    #[cfg(feature = "preview")]
+    preview::set_protocol(args.preview_protocol);
+
+    match args.command {
+        CliCommand::Read {
+            dump_path,
+            read_args,
+        } => read_document(read_args, dump_path),
+        CliCommand::Dump {
+            dump_path,
+            read_args,
+        } => read_document(read_args, Some(dump_path)),
+        CliCommand::Watch {
+            dump_path,
+            read_options,
+        } => watch_documents(&read_options, &dump_path),
+        CliCommand::ListReaders { backend } => list_readers(backend.as_ref()),
+        CliCommand::Inspect { dump_path } => inspect_dump(&dump_path),
+        CliCommand::Verify { dump_path } => verify_dump(&dump_path),
+        CliCommand::MrzCheck { mrz } => check_mrz(&mrz),
+        CliCommand::Vds {
+            vds_path,
+            trust_store_path,
+        } => {
+            // Decode the VDS instead of reading an eMRTD.
+            #[cfg(feature = "vds")]
+            decode_vds(&vds_path, &trust_store_path);
+            #[cfg(not(feature = "vds"))]
+            {
+                let _ = (vds_path, trust_store_path);
+                error!("Cannot decode VDS, feature was disabled at compile-time.");
+            }
+        }
+    }
+}
+
+/// Parses and prints an MRZ, panicking if it can't be parsed.
+fn parse_mrz(mrz_lines: &[String]) -> types::MRZ {
+    return try_parse_mrz(mrz_lines)
+        .expect("Couldn't parse the given MRZ, is it TD1, TD2, TD3, MRV-A or MRV-B?");
+}
+
+/// Parses and prints an MRZ.
+fn try_parse_mrz(mrz_lines: &[String]) -> Option<types::MRZ> {
+    let mrz_obj = types::MRZ::deserialize(&mrz_lines.concat())?;
+    #[cfg(feature = "cli")]
+    {
+        info!("");
+        info!("{}", dg_helpers::pad_section_title("MRZ"));
+        info!("{}", dg_helpers::pad_section_subtitle("Supplied MRZ"));
+        info!("");
+        mrz_obj.fancy_print();
+        info!("");
+    }
+    return Some(mrz_obj);
+}
+
+fn check_mrz(mrz_lines: &[String]) {
+    let mrz_obj = match try_parse_mrz(mrz_lines) {
+        Some(mrz_obj) => mrz_obj,
+        None => {
+            error!("<red>Couldn't parse the given MRZ, is it TD1, TD2, TD3, MRV-A or MRV-B?</>");
+            return;
+        }
+    };
+    let check_digits = mrz_obj.validate_check_digits(false);
+    let invalid_check_digits = check_digits.iter().filter(|valid| !**valid).count();
+    if invalid_check_digits == 0 {
+        info!(
+            "<green>All {} check digits of the {} MRZ are valid.</>",
+            check_digits.len(),
+            mrz_obj.format_name()
+        );
+    } else {
+        error!(
+            "<red>{} of {} check digits of the {} MRZ are invalid.</>",
+            invalid_check_digits,
+            check_digits.len(),
+            mrz_obj.format_name()
+        );
+    }
+}
+
+fn list_readers(backend: Option<&ReaderInterface>) {
+    let readers = match backend {
+        Some(backend) => backend.list_readers(),
+        None => smartcard_abstractions::list_all_readers(),
+    };
+    if readers.is_empty() {
+        warn!("No readers found.");
+        return;
+    }
+    for reader in readers.iter() {
+        info!("<b>{}</> ({})", reader.name, reader.backend);
+        let card_present = match reader.card_present {
+            Some(true) => "Yes",
+            Some(false) => "No",
+            None => "Unknown",
+        };
+        dg_helpers::print_string_element("Card present", &card_present.to_string());
+        dg_helpers::print_option_binary_element("ATR", &reader.atr);
+        dg_helpers::print_option_string_element(
+            "Capabilities version",
+            &reader
+                .capabilities_version
+                .map(|version| version.to_string()),
+        );
+        dg_helpers::print_option_string_element("Firmware version", &reader.firmware_version);
+    }
+    info!("(Hint: You can select a specific reader with read --reader)");
+}
+
+/// Decodes DG13 with the layouts for the issuing state and prints it.
+///
+/// The issuing state is taken from DG1, or from the printed MRZ if DG1 couldn't be read.
+fn decode_dg13(
+    mut ef_dg13_file: types::EFDG13,
+    dg_info: &types::DataGroup,
+    parsed_data_groups: &[types::ParsedDataGroup],
+    printed_mrz: Option<&types::MRZ>,
+) -> types::ParsedDataGroup {
+    let issuing_state = parsed_data_groups
+        .iter()
+        .find_map(|parsed_data| match parsed_data {
+            types::ParsedDataGroup::EFDG1(ef_dg1_file) => Some(ef_dg1_file.mrz.issuing_state()),
+            _ => None,
+        })
+        .or(printed_mrz.map(|mrz_obj| mrz_obj.issuing_state()));
+    ef_dg13_file.decode_fields(issuing_state.map(|state| state.as_str()));
+    #[cfg(feature = "cli")]
+    ef_dg13_file.fancy_print(dg_info);
+    return types::ParsedDataGroup::EFDG13(ef_dg13_file);
+}
+
+/// Groups dumped files by the document they belong to.
+fn group_dumped_files(dumped_files: &[helpers::DumpedFile]) -> Vec<&[helpers::DumpedFile]> {
+    return dumped_files
+        .chunk_by(|a, b| a.filename_distinguisher == b.filename_distinguisher)
+        .collect();
+}
+
+fn inspect_dump(dump_path: &Path) {
+    let dumped_files =
+        helpers::find_dumped_files(dump_path).expect("Couldn't read the given directory.");
+    if dumped_files.is_empty() {
+        error!("No dumped files found in {}.", dump_path.to_string_lossy());
+        return;
+    }
+    for document_files in group_dumped_files(&dumped_files) {
+        info!("");
+        info!(
+            "{}",
+            dg_helpers::pad_section_title(&document_files[0].filename_distinguisher)
+        );
+
+        let mut parsed_data_groups: Vec<types::ParsedDataGroup> = vec![];
+        for dumped_file in document_files.iter() {
+            let file_data = match std::fs::read(&dumped_file.path) {
+                Ok(file_data) => file_data,
+                Err(err) => {
+                    error!(
+                        "Couldn't read {}: {}",
+                        dumped_file.path.to_string_lossy(),
+                        err
+                    );
+                    continue;
+                }
+            };
+            // DG13's layout depends on the issuing state, so it's printed once it's decoded with it.
+            let print_data = dumped_file.dg_info.dg_num != 13;
+            let parsed_data =
+                match (dumped_file.dg_info.parser)(&file_data, dumped_file.dg_info, print_data) {
+                    Some(types::ParsedDataGroup::EFDG13(ef_dg13_file)) => Some(decode_dg13(
+                        ef_dg13_file,
+                        dumped_file.dg_info,
+                        &parsed_data_groups,
+                        None,
+                    )),
+                    parsed_data => parsed_data,
+                };
+            match parsed_data {
+                Some(parsed_data) => parsed_data_groups.push(parsed_data),
+                None => {}
+            }
+        }
+
+        let consistency_report = types::ConsistencyReport::check(None, &parsed_data_groups);
+        #[cfg(feature = "cli")]
+        consistency_report.fancy_print();
+        if consistency_report.has_mismatches() {
+            warn!("<red>Document data is inconsistent, see the consistency findings above.</>");
+        }
+    }
+}
+
+fn verify_dump(dump_path: &Path) {
+    let dumped_files =
+        helpers::find_dumped_files(dump_path).expect("Couldn't read the given directory.");
+    if dumped_files.is_empty() {
+        error!("No dumped files found in {}.", dump_path.to_string_lossy());
+        return;
+    }
+    for document_files in group_dumped_files(&dumped_files) {
+        let filename_distinguisher = &document_files[0].filename_distinguisher;
+        info!("Verifying {}", filename_distinguisher);
+
+        let ef_sod_file = document_files
+            .iter()
+            .find(|dumped_file| dumped_file.dg_info.name == "EF.SOD")
+            .and_then(|dumped_file| std::fs::read(&dumped_file.path).ok())
+            .and_then(|file_data| {
+                dg_parsers::ef_sod::parser(
+                    &file_data,
+                    &types::DATA_GROUPS[DataGroupEnum::EFSod as usize],
+                    false,
+                )
+            });
+        let ef_sod_file = match ef_sod_file {
+            Some(types::ParsedDataGroup::EFSod(ef_sod_file)) => ef_sod_file,
+            _ => {
+                error!(
+                    "<red>Couldn't read EF.SOD of {}, cannot verify it.</>",
+                    filename_distinguisher
+                );
+                continue;
+            }
+        };
+
+        // TODO: Verify the signature of EF.SOD against the Document Signer Certificate
+        warn!(
+            "EF.SOD's signature isn't verified yet, so this only checks that the files match it."
+        );
+
+        let mut mismatches = 0;
+        for dumped_file in document_files.iter() {
+            if !dumped_file.dg_info.in_lds1 || dumped_file.dg_info.dg_num == 0 {
+                continue;
+            }
+            let file_data = match std::fs::read(&dumped_file.path) {
+                Ok(file_data) => file_data,
+                Err(err) => {
+                    error!(
+                        "Couldn't read {}: {}",
+                        dumped_file.path.to_string_lossy(),
+                        err
+                    );
+                    continue;
+                }
+            };
+            match ef_sod_file.verify_data_group(dumped_file.dg_info.dg_num, &file_data) {
+                Some(true) => info!("{} matches its hash.", dumped_file.dg_info.name),
+                Some(false) => {
+                    mismatches += 1;
+                    error!(
+                        "<red>{} doesn't match its hash in EF.SOD.</>",
+                        dumped_file.dg_info.name
+                    );
+                }
+                None => warn!(
+                    "{} has no hash in EF.SOD, or the hash algorithm isn't supported.",
+                    dumped_file.dg_info.name
+                ),
+            }
+        }
+        for data_group_hash in ef_sod_file.data_group_hashes.iter() {
+            let dumped = document_files.iter().any(|dumped_file| {
+                dumped_file.dg_info.in_lds1 && dumped_file.dg_info.dg_num == data_group_hash.dg_num
+            });
+            if !dumped {
+                info!(
+                    "<d>EF.DG{} has a hash in EF.SOD, but wasn't dumped.</>",
+                    data_group_hash.dg_num
+                );
+            }
+        }
+
+        // Without a verified signature, matching hashes don't mean the document is genuine, so this isn't green.
+        if mismatches == 0 {
+            info!(
+                "All dumped data groups of {} match EF.SOD (its signature is unverified).",
+                filename_distinguisher
+            );
+        } else {
+            error!(
+                "<red>{} dumped data groups of {} don't match EF.SOD.</>",
+                mismatches, filename_distinguisher
+            );
+        }
+    }
+}
+
+/// Reads a document, dumping its files if a path is given.
+fn read_document(args: ReadArgs, dump_path: Option<PathBuf>) {
+    // Parse the MRZ if one was supplied, and use it as the source of BAC credentials.
+    let credentials = match args.mrz.as_ref() {
+        Some(mrz_lines) => Credentials::from_mrz(parse_mrz(mrz_lines)),
+        None => Credentials {
+            printed_mrz: None,
+            document_number: args.document_number,
+            date_of_birth: args.date_of_birth,
+            date_of_expiry: args.date_of_expiry,
+            card_access_number: args.card_access_number,
+        },
+    };
+
+    // The document number is padded with fillers for BAC, which don't belong in filenames.
+    let filename_distinguisher = match credentials.document_number.as_ref() {
+        Some(document_number) => document_number.trim_end_matches('<').to_string(),
+        None => helpers::unix_time().to_string(),
     };
 
     // Connect to given reader
     let mut interface = args
+        .read_options
         .backend
-        .connect(&args.reader)
+        .connect(&args.read_options.reader)
         .expect("Couldn't find given interface.");
 
     // Select a nearby eMRTD
@@ -90,13 +543,162 @@ fn main() {
         .select()
         .expect("Couldn't select an eMRTD in range.");
 
+    match read_selected_document(
+        &mut smartcard,
+        &args.read_options,
+        &credentials,
+        &filename_distinguisher,
+        &dump_path,
+    ) {
+        Ok(read_result) => read_result.report_unread_applications(),
+        Err(err) => error!("<red>Reading the document failed: {}</>", err),
+    }
+}
+
+/// Reads MRZ lines from stdin, until there's enough of them for the MRZ format of the first one.
+///
+/// Returns None if stdin was closed.
+fn read_mrz_from_stdin() -> Option<Vec<String>> {
+    let mut mrz_lines: Vec<String> = vec![];
+    for line in io::stdin().lock().lines() {
+        let line = line.ok()?.trim().to_string();
+        if line.is_empty() {
+            continue;
+        }
+        mrz_lines.push(line);
+        // TD1 has three lines of 30 characters, the rest have two.
+        let line_count = match mrz_lines[0].len() {
+            30 => 3,
+            _ => 2,
+        };
+        if mrz_lines.len() == line_count {
+            return Some(mrz_lines);
+        }
+    }
+    return None;
+}
+
+/// Waits for documents to be placed on the reader, and reads each one.
+///
+/// The MRZ of each document is read from stdin, so that an MRZ scanner that acts as a keyboard can be used.
+/// Files are dumped with the document number and the time of reading, so that reading the same document again doesn't overwrite them.
+fn watch_documents(read_options: &ReadOptions, dump_path: &Path) {
+    let mut interface = read_options
+        .backend
+        .connect(&read_options.reader)
+        .expect("Couldn't find given interface.");
+    let dump_path = Some(dump_path.to_path_buf());
+
+    loop {
+        info!("Waiting for a document...");
+        if !interface.wait_for_card(true) {
+            error!("Can't wait for documents on this reader anymore, stopping.");
+            return;
+        }
+
+        info!("Found a document, enter its MRZ (one line per line):");
+        let mrz_lines = match read_mrz_from_stdin() {
+            Some(mrz_lines) => mrz_lines,
+            None => {
+                info!("stdin was closed, stopping.");
+                return;
+            }
+        };
+        match try_parse_mrz(&mrz_lines) {
+            Some(mrz_obj) => {
+                let filename_distinguisher =
+                    format!("{}-{}", mrz_obj.document_number(), helpers::unix_time());
+                let credentials = Credentials::from_mrz(mrz_obj);
+
+                match interface.select() {
+                    Some(mut smartcard) => {
+                        let read_result = read_selected_document(
+                            &mut smartcard,
+                            read_options,
+                            &credentials,
+                            &filename_distinguisher,
+                            &dump_path,
+                        );
+                        match read_result {
+                            Ok(read_result) => {
+                                read_result.report_unread_applications();
+                                info!(
+                                    "<green>Finished reading the document, files were dumped as {}.</>",
+                                    filename_distinguisher
+                                );
+                            }
+                            Err(err) if err.is::<types::CardLostError>() => error!(
+                                "<red>The document stopped answering, was it removed too early? Files that were read are dumped as {}.</>",
+                                filename_distinguisher
+                            ),
+                            Err(err) => error!(
+                                "<red>Reading the document failed: {} Files that were read are dumped as {}.</>",
+                                err, filename_distinguisher
+                            ),
+                        }
+                    }
+                    None => error!("<red>Couldn't select the document, was it removed?</>"),
+                }
+            }
+            None => {
+                error!("<red>Couldn't parse the given MRZ, is it TD1, TD2, TD3, MRV-A or MRV-B?</>")
+            }
+        }
+
+        info!("Remove the document to read the next one.");
+        if !interface.wait_for_card(false) {
+            error!("Can't wait for documents on this reader anymore, stopping.");
+            return;
+        }
+    }
+}
+
+/// Applications found on a document while reading it.
+struct ReadResult {
+    /// Applications listed in EF.DIR, empty if the document has none
+    applications: Vec<types::Application>,
+    /// AIDs of the applications that were read
+    read_applications: Vec<Vec<u8>>,
+}
+
+impl ReadResult {
+    /// Logs the applications listed in EF.DIR that weren't read.
+    fn report_unread_applications(&self) {
+        for application in self.applications.iter() {
+            if self.read_applications.contains(&application.aid) {
+                continue;
+            }
+            let application_name =
+                icao9303::get_application_name(&application.aid).unwrap_or("Unknown");
+            info!(
+                "Found {} application ({:02x?}), reading it isn't supported yet.",
+                application_name, application.aid
+            );
+        }
+    }
+}
+
+/// Reads a selected document, dumping its files if a path is given.
+///
+/// Returns an error if the document can't be read, including when it stops answering midway.
+fn read_selected_document(
+    smartcard: &mut Box<dyn smartcard_abstractions::Smartcard + '_>,
+    read_options: &ReadOptions,
+    credentials: &Credentials,
+    filename_distinguisher: &String,
+    dump_path: &Option<PathBuf>,
+) -> Result<ReadResult, Box<dyn Error>> {
     // Read EF.CardAccess
+    // Until we've read EF.ATR/INFO, we don't know what APDU sizes the chip can handle.
+    let mut apdu_limits = iso7816::ApduLimits::default();
+
     let (_, file_read, _) = helpers::read_file_by_name(
-        &mut smartcard,
+        smartcard,
         DataGroupEnum::EFCardAccess,
-        &filename_distinguisher,
-        &args.dump_path,
-    );
+        filename_distinguisher,
+        dump_path,
+        &apdu_limits,
+    )?;
     // TODO: Use parsed_data.is_some() here when we finally can parse EF.CardAccess
     let pace_available = file_read.is_some();
     if !pace_available {
@@ -104,6 +706,7 @@ fn main() {
     }
 
     // Read all files under the master file
+    let mut ef_dir_file: Option<types::EFDir> = None;
     for dg_info in types::DATA_GROUPS.iter() {
         if dg_info.name == "EF.CardAccess"
             || dg_info.in_lds1
@@ -111,74 +714,180 @@ fn main() {
         {
             continue;
         }
-        helpers::read_file(
-            &mut smartcard,
+        let (_, parsed_data) = helpers::read_file(
+            smartcard,
             dg_info,
-            &filename_distinguisher,
-            &args.dump_path,
-        );
+            filename_distinguisher,
+            dump_path,
+            &apdu_limits,
+        )?;
+        match parsed_data {
+            Some(types::ParsedDataGroup::EFAtrInfo(file)) => {
+                apdu_limits = file.apdu_limits();
+                // A reader that can't handle extended lengths just fails the exchange, so they're opt-in.
+                apdu_limits.extended_length &= read_options.extended_length;
+            }
+            Some(types::ParsedDataGroup::EFDir(file)) => ef_dir_file = Some(file),
+            _ => {}
+        }
     }
 
-    // Select eMRTD applet
-    info!("Selecting eMRTD LDS1 applet");
-    let _ = iso7816::apdu_select_file_by_name(icao9303::AID_MRTD_LDS1.to_vec())
-        .exchange(&mut smartcard, true);
+    // EF.DIR lists the applications on the chip, so we read the ones we support from it.
+    // It's optional and issuer-specific though, so without it we try LDS1, which every eMRTD has.
+    let mut applications_to_read: Vec<&[u8]> = match ef_dir_file.as_ref() {
+        Some(ef_dir_file) => icao9303::SUPPORTED_APPLICATIONS
+            .iter()
+            .copied()
+            .filter(|aid| ef_dir_file.has_application(aid))
+            .collect(),
+        None => vec![],
+    };
+    if applications_to_read.is_empty() {
+        if ef_dir_file.is_some() {
+            warn!("EF.DIR doesn't list an application we can read, trying to select eMRTD LDS1 anyway.");
+        }
+        applications_to_read.push(&icao9303::AID_MRTD_LDS1);
+    }
+
+    let mut read_result = ReadResult {
+        applications: match ef_dir_file {
+            Some(ef_dir_file) => ef_dir_file.applications,
+            None => vec![],
+        },
+        read_applications: vec![],
+    };
+    for aid in applications_to_read {
+        let application_name = icao9303::get_application_name(aid).unwrap_or("Unknown");
+        info!("Selecting {} application", application_name);
+        let (_, status_code) =
+            iso7816::apdu_select_file_by_name(aid.to_vec()).exchange(smartcard, false)?;
+        if status_code != iso7816::StatusCode::Ok as u16 {
+            return Err(Box::new(types::ReadError {
+                additional_text: format!(
+                    "couldn't select the {} application ({:04x}).",
+                    application_name, status_code
+                ),
+            }));
+        }
+
+        if aid == icao9303::AID_MRTD_LDS1 {
+            read_lds1_application(
+                smartcard,
+                read_options,
+                credentials,
+                filename_distinguisher,
+                dump_path,
+                pace_available,
+                &apdu_limits,
+            )?;
+        }
+        read_result.read_applications.push(aid.to_vec());
+    }
+    return Ok(read_result);
+}
 
+/// Authenticates to the selected eMRTD LDS1 application, and reads its files.
+fn read_lds1_application(
+    smartcard: &mut Box<dyn smartcard_abstractions::Smartcard + '_>,
+    read_options: &ReadOptions,
+    credentials: &Credentials,
+    filename_distinguisher: &String,
+    dump_path: &Option<PathBuf>,
+    pace_available: bool,
+    apdu_limits: &iso7816::ApduLimits,
+) -> Result<(), Box<dyn Error>> {
     // Authenticate
-    if args.card_access_number.is_some() {
-        panic!("PACE/CAN isn't implemented, cannot proceed with authentication.");
+    if credentials.card_access_number.is_some() {
+        return Err(Box::new(types::ReadError {
+            additional_text: "PACE/CAN isn't implemented, cannot proceed with authentication."
+                .to_string(),
+        }));
     }
     let (ks_enc, ks_mac, mut ssc) = icao9303::do_authentication(
         pace_available,
-        &mut smartcard,
-        &args.document_number.as_ref().unwrap(),
-        &args.date_of_birth.unwrap(),
-        &args.date_of_expiry.unwrap(),
-    );
+        smartcard,
+        credentials.document_number.as_ref().unwrap(),
+        credentials.date_of_birth.as_ref().unwrap(),
+        credentials.date_of_expiry.as_ref().unwrap(),
+    )?;
 
     // Read EF.COM, which contains a file list
     let (_, _, parse_result) = helpers::secure_read_file_by_name(
-        &mut smartcard,
+        smartcard,
         DataGroupEnum::EFCom,
-        &filename_distinguisher,
-        &args.dump_path,
+        filename_distinguisher,
+        dump_path,
+        apdu_limits,
         true,
         &mut ssc,
         &ks_enc,
         &ks_mac,
-    );
-    let parsed_ef_com = parse_result.unwrap();
-    let ef_com_file: types::EFCom = match parsed_ef_com {
-        types::ParsedDataGroup::EFCom(ef_com_file) => ef_com_file,
+    )?;
+    let ef_com_file: types::EFCom = match parse_result {
+        Some(types::ParsedDataGroup::EFCom(ef_com_file)) => ef_com_file,
         _ => {
-            panic!("Expected EFCom but got {:x?}", parsed_ef_com);
+            return Err(Box::new(types::ReadError {
+                additional_text: "couldn't read EF.COM, so we don't know which files to read."
+                    .to_string(),
+            }));
         }
     };
 
-    // read all files under the LDS1 file
+    // read the selected files under the LDS1 file
+    let data_group_selection = types::DataGroupSelection {
+        only: read_options.only_data_groups.clone(),
+        skip: read_options.skip_data_groups.clone(),
+    };
+    // EF.COM only lists data groups, so EF.SOD isn't in it.
+    let mut parsed_data_groups: Vec<types::ParsedDataGroup> = vec![];
     for dg_info in types::DATA_GROUPS.iter() {
         if dg_info.name == "EF.COM"
             || !dg_info.in_lds1
             || dg_info.pace_only
-            || (dg_info.is_binary && args.dump_path.is_none())
-            || !ef_com_file.data_group_tag_list.contains(&dg_info.tag)
+            || !data_group_selection.includes(dg_info, dump_path.is_some())
+            || (dg_info.name != "EF.SOD" && !ef_com_file.data_group_tag_list.contains(&dg_info.tag))
         {
             continue;
         }
 
-        helpers::secure_read_file(
-            &mut smartcard,
+        // DG13's layout depends on the issuing state, so it's printed once it's decoded with it.
+        let print_data = dg_info.dg_num != 13;
+        let (_, parsed_data) = helpers::secure_read_file(
+            smartcard,
             dg_info,
-            &filename_distinguisher,
-            &args.dump_path,
+            filename_distinguisher,
+            dump_path,
+            apdu_limits,
+            print_data,
             true,
             &mut ssc,
             &ks_enc,
             &ks_mac,
-        );
+        )?;
+        let parsed_data = match parsed_data {
+            Some(types::ParsedDataGroup::EFDG13(ef_dg13_file)) => Some(decode_dg13(
+                ef_dg13_file,
+                dg_info,
+                &parsed_data_groups,
+                credentials.printed_mrz.as_ref(),
+            )),
+            parsed_data => parsed_data,
+        };
+        match parsed_data {
+            Some(parsed_data) => parsed_data_groups.push(parsed_data),
+            None => {}
+        }
     }
 
-    // TODO: Read EF_SOD and compare hashes of files
+    // Cross-check the printed MRZ and the data groups against each other.
+    let consistency_report =
+        types::ConsistencyReport::check(credentials.printed_mrz.as_ref(), &parsed_data_groups);
+    #[cfg(feature = "cli")]
+    consistency_report.fancy_print();
+    if consistency_report.has_mismatches() {
+        warn!("<red>Document data is inconsistent, see the consistency findings above.</>");
+    }
 
-    drop(smartcard);
+    // TODO: Read EF_SOD and compare hashes of files
+    return Ok(());
 }
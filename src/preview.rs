@@ -0,0 +1,382 @@
+//! Terminal previews of images, through the kitty graphics protocol, sixel or half blocks.
+#[cfg(feature = "jpeg2000")]
+use crate::dg_parsers::jpeg2000;
+use crate::types;
+use simplelog::warn;
+use std::{
+    env, fmt,
+    io::{self, IsTerminal, Write},
+    str::FromStr,
+    sync::OnceLock,
+};
+use zune_jpeg::zune_core::{bytestream::ZCursor, colorspace::ColorSpace, options::DecoderOptions};
+
+// Used when the terminal doesn't tell us its size.
+const DEFAULT_TERMINAL_COLUMNS: usize = 80;
+const DEFAULT_CELL_PIXEL_WIDTH: usize = 8;
+// The kitty graphics protocol limits the payload of each escape code.
+const KITTY_CHUNK_LEN: usize = 4096;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PreviewProtocol {
+    /// Picks one based on the terminal, or turns previews off if stdout isn't a terminal
+    Auto,
+    Kitty,
+    Sixel,
+    /// Unicode half blocks with 24 bit colours, which most terminals support
+    Blocks,
+    Off,
+}
+
+impl fmt::Display for PreviewProtocol {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PreviewProtocol::Auto => write!(f, "auto"),
+            PreviewProtocol::Kitty => write!(f, "kitty"),
+            PreviewProtocol::Sixel => write!(f, "sixel"),
+            PreviewProtocol::Blocks => write!(f, "blocks"),
+            PreviewProtocol::Off => write!(f, "off"),
+        }
+    }
+}
+
+impl FromStr for PreviewProtocol {
+    type Err = types::ParseError;
+    fn from_str(name: &str) -> Result<PreviewProtocol, Self::Err> {
+        match name {
+            "auto" => Ok(PreviewProtocol::Auto),
+            "kitty" => Ok(PreviewProtocol::Kitty),
+            "sixel" => Ok(PreviewProtocol::Sixel),
+            "blocks" => Ok(PreviewProtocol::Blocks),
+            "off" => Ok(PreviewProtocol::Off),
+            _ => Err(types::ParseError {}),
+        }
+    }
+}
+
+static PREVIEW_PROTOCOL: OnceLock<PreviewProtocol> = OnceLock::new();
+
+/// Sets the protocol to print previews with. Only the first call has an effect.
+pub fn set_protocol(protocol: PreviewProtocol) {
+    let _ = PREVIEW_PROTOCOL.set(protocol);
+}
+
+/// Guesses the best supported protocol from the environment.
+fn detect_protocol() -> PreviewProtocol {
+    if !io::stdout().is_terminal() {
+        return PreviewProtocol::Off;
+    }
+    let term = env::var("TERM").unwrap_or_default();
+    let term_program = env::var("TERM_PROGRAM").unwrap_or_default();
+    if env::var_os("KITTY_WINDOW_ID").is_some()
+        || term == "xterm-kitty"
+        || term == "xterm-ghostty"
+        || term_program == "WezTerm"
+        || term_program == "ghostty"
+    {
+        return PreviewProtocol::Kitty;
+    }
+    if term.contains("sixel")
+        || term.starts_with("foot")
+        || term.starts_with("mlterm")
+        || term_program == "iTerm.app"
+    {
+        return PreviewProtocol::Sixel;
+    }
+    return PreviewProtocol::Blocks;
+}
+
+/// Returns the terminal's width in columns and, if the terminal reports it, in pixels.
+fn terminal_width() -> (usize, Option<usize>) {
+    #[cfg(unix)]
+    {
+        let mut window_size: libc::winsize = unsafe { std::mem::zeroed() };
+        let result =
+            unsafe { libc::ioctl(libc::STDOUT_FILENO, libc::TIOCGWINSZ, &mut window_size) };
+        if result == 0 && window_size.ws_col != 0 {
+            let pixel_width = match window_size.ws_xpixel {
+                0 => None,
+                pixel_width => Some(pixel_width as usize),
+            };
+            return (window_size.ws_col as usize, pixel_width);
+        }
+    }
+    let columns = env::var("COLUMNS")
+        .ok()
+        .and_then(|columns| columns.parse::<usize>().ok())
+        .unwrap_or(DEFAULT_TERMINAL_COLUMNS);
+    return (columns, None);
+}
+
+fn encode_base64(data: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut encoded = String::with_capacity(data.len().div_ceil(3) * 4);
+    for chunk in data.chunks(3) {
+        let bytes = [
+            chunk[0],
+            *chunk.get(1).unwrap_or(&0),
+            *chunk.get(2).unwrap_or(&0),
+        ];
+        let triple = u32::from_be_bytes([0, bytes[0], bytes[1], bytes[2]]);
+        for i in 0..4 {
+            match i <= chunk.len() {
+                true => encoded.push(ALPHABET[(triple >> (18 - 6 * i) & 0x3F) as usize] as char),
+                false => encoded.push('='),
+            }
+        }
+    }
+    return encoded;
+}
+
+/// An 8 bit RGB image.
+struct RgbImage {
+    width: usize,
+    height: usize,
+    pixels: Vec<u8>,
+}
+
+impl RgbImage {
+    fn decode(data: &[u8], image_format: types::BiometricImageFormat) -> Option<RgbImage> {
+        match image_format {
+            types::BiometricImageFormat::Jpeg => {
+                let options = DecoderOptions::default().jpeg_set_out_colorspace(ColorSpace::RGB);
+                let mut decoder =
+                    zune_jpeg::JpegDecoder::new_with_options(ZCursor::new(data), options);
+                let pixels = match decoder.decode() {
+                    Ok(pixels) => pixels,
+                    Err(err) => {
+                        warn!("Couldn't decode JPEG image for preview: {:?}", err);
+                        return None;
+                    }
+                };
+                let (width, height) = decoder.dimensions()?;
+                return Some(RgbImage {
+                    width: width,
+                    height: height,
+                    pixels: pixels,
+                });
+            }
+            #[cfg(feature = "jpeg2000")]
+            types::BiometricImageFormat::Jpeg2000 => {
+                let (width, height, pixels) = jpeg2000::decode_rgb(data)?;
+                return Some(RgbImage {
+                    width: width,
+                    height: height,
+                    pixels: pixels,
+                });
+            }
+            _ => return None,
+        }
+    }
+
+    fn pixel(&self, x: usize, y: usize) -> [u8; 3] {
+        let offset = (y * self.width + x) * 3;
+        return [
+            self.pixels[offset],
+            self.pixels[offset + 1],
+            self.pixels[offset + 2],
+        ];
+    }
+
+    /// Downscales the image by averaging the pixels each new pixel covers.
+    fn resize(&self, width: usize, height: usize) -> RgbImage {
+        let mut pixels: Vec<u8> = Vec::with_capacity(width * height * 3);
+        for y in 0..height {
+            let (y_start, y_end) = (
+                y * self.height / height,
+                ((y + 1) * self.height).div_ceil(height),
+            );
+            for x in 0..width {
+                let (x_start, x_end) = (
+                    x * self.width / width,
+                    ((x + 1) * self.width).div_ceil(width),
+                );
+                let mut sums = [0usize; 3];
+                for source_y in y_start..y_end {
+                    for source_x in x_start..x_end {
+                        for (sum, value) in sums.iter_mut().zip(self.pixel(source_x, source_y)) {
+                            *sum += value as usize;
+                        }
+                    }
+                }
+                let count = (y_end - y_start) * (x_end - x_start);
+                pixels.extend(sums.map(|sum| (sum / count) as u8));
+            }
+        }
+        return RgbImage {
+            width: width,
+            height: height,
+            pixels: pixels,
+        };
+    }
+
+    /// Scales the image down to the given width, keeping its aspect ratio.
+    fn fit_width(&self, width: usize, height_multiple: usize) -> RgbImage {
+        let width = width.clamp(1, self.width);
+        let height = (self.height * width / self.width).max(height_multiple);
+        return self.resize(width, height - height % height_multiple);
+    }
+
+    fn render_kitty(&self) -> String {
+        let payload = encode_base64(&self.pixels);
+        let chunks: Vec<&[u8]> = payload.as_bytes().chunks(KITTY_CHUNK_LEN).collect();
+        let mut output = String::new();
+        for (i, chunk) in chunks.iter().enumerate() {
+            let more_chunks = (i + 1 != chunks.len()) as u8;
+            match i {
+                0 => output.push_str(&format!(
+                    "\x1b_Ga=T,q=2,f=24,s={},v={},m={};",
+                    self.width, self.height, more_chunks
+                )),
+                _ => output.push_str(&format!("\x1b_Gm={};", more_chunks)),
+            }
+            output.push_str(&String::from_utf8_lossy(chunk));
+            output.push_str("\x1b\\");
+        }
+        output.push('\n');
+        return output;
+    }
+
+    /// Renders the image as sixels, with a 6x6x6 colour cube as the palette.
+    fn render_sixel(&self) -> String {
+        let palette_index = |[r, g, b]: [u8; 3]| -> usize {
+            let quantize = |value: u8| (value as usize * 5 + 127) / 255;
+            return quantize(r) * 36 + quantize(g) * 6 + quantize(b);
+        };
+        let indexes: Vec<usize> = self
+            .pixels
+            .chunks_exact(3)
+            .map(|pixel| palette_index([pixel[0], pixel[1], pixel[2]]))
+            .collect();
+
+        let mut output = format!("\x1bPq\"1;1;{};{}", self.width, self.height);
+        for index in 0..216 {
+            let level = |value: usize| value * 100 / 5;
+            output.push_str(&format!(
+                "#{};2;{};{};{}",
+                index,
+                level(index / 36),
+                level(index / 6 % 6),
+                level(index % 6)
+            ));
+        }
+        for band_start in (0..self.height).step_by(6) {
+            let band_end = (band_start + 6).min(self.height);
+            let mut colours: Vec<usize> = (band_start..band_end)
+                .flat_map(|y| {
+                    indexes[y * self.width..(y + 1) * self.width]
+                        .iter()
+                        .copied()
+                })
+                .collect();
+            colours.sort_unstable();
+            colours.dedup();
+            for colour in colours {
+                output.push_str(&format!("#{}", colour));
+                let sixels: Vec<char> = (0..self.width)
+                    .map(|x| {
+                        let bits = (band_start..band_end)
+                            .filter(|y| indexes[y * self.width + x] == colour)
+                            .fold(0u8, |bits, y| bits | 1 << (y - band_start));
+                        return (63 + bits) as char;
+                    })
+                    .collect();
+                // Run-length encode repeated sixels.
+                let mut x = 0;
+                while x < sixels.len() {
+                    let run_len = sixels[x..]
+                        .iter()
+                        .take_while(|sixel| **sixel == sixels[x])
+                        .count();
+                    match run_len {
+                        1..=3 => output.extend(std::iter::repeat_n(sixels[x], run_len)),
+                        _ => output.push_str(&format!("!{}{}", run_len, sixels[x])),
+                    }
+                    x += run_len;
+                }
+                output.push('$');
+            }
+            output.push('-');
+        }
+        output.push_str("\x1b\\\n");
+        return output;
+    }
+
+    /// Renders the image with upper half blocks, two pixel rows per line of text.
+    fn render_blocks(&self) -> String {
+        let mut output = String::new();
+        for y in (0..self.height).step_by(2) {
+            for x in 0..self.width {
+                let [top_r, top_g, top_b] = self.pixel(x, y);
+                let [bottom_r, bottom_g, bottom_b] = self.pixel(x, (y + 1).min(self.height - 1));
+                output.push_str(&format!(
+                    "\x1b[38;2;{};{};{}m\x1b[48;2;{};{};{}m▀",
+                    top_r, top_g, top_b, bottom_r, bottom_g, bottom_b
+                ));
+            }
+            output.push_str("\x1b[0m\n");
+        }
+        return output;
+    }
+}
+
+/// Prints a preview of the image to the terminal, scaled to fit its width.
+pub fn print_image(data: &[u8], image_format: types::BiometricImageFormat) {
+    let protocol = match PREVIEW_PROTOCOL.get() {
+        Some(PreviewProtocol::Auto) | None => detect_protocol(),
+        Some(protocol) => *protocol,
+    };
+    if protocol == PreviewProtocol::Off {
+        return;
+    }
+    let image = match RgbImage::decode(data, image_format) {
+        Some(image) => image,
+        None => return,
+    };
+
+    let (columns, pixel_width) = terminal_width();
+    let preview = match protocol {
+        PreviewProtocol::Kitty => image
+            .fit_width(pixel_width.unwrap_or(columns * DEFAULT_CELL_PIXEL_WIDTH), 1)
+            .render_kitty(),
+        PreviewProtocol::Sixel => image
+            .fit_width(pixel_width.unwrap_or(columns * DEFAULT_CELL_PIXEL_WIDTH), 6)
+            .render_sixel(),
+        _ => image.fit_width(columns, 2).render_blocks(),
+    };
+
+    let mut stdout = io::stdout().lock();
+    match stdout
+        .write_all(preview.as_bytes())
+        .and_then(|_| stdout.flush())
+    {
+        Ok(()) => {}
+        Err(err) => warn!("Couldn't print image preview: {}", err),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn preview_rendering() {
+        // 4x2, left half red and right half blue
+        let image = RgbImage {
+            width: 4,
+            height: 2,
+            pixels: [[255, 0, 0], [255, 0, 0], [0, 0, 255], [0, 0, 255]]
+                .repeat(2)
+                .concat(),
+        };
+        let image = image.resize(2, 2);
+        assert_eq!(image.pixels, [255, 0, 0, 0, 0, 255].repeat(2));
+        assert_eq!(
+            image.render_blocks(),
+            "\x1b[38;2;255;0;0m\x1b[48;2;255;0;0m▀\x1b[38;2;0;0;255m\x1b[48;2;0;0;255m▀\x1b[0m\n"
+        );
+        // Red is colour 180 and blue is colour 5, both filling the top two rows of the band.
+        assert!(image.render_sixel().ends_with("#5?B$#180B?$-\x1b\\\n"));
+        assert_eq!(encode_base64(b"pass"), "cGFzcw==");
+    }
+}
@@ -1,8 +1,8 @@
 use super::comms::{open_serial_comms, send_and_get_command, send_command};
 use super::helpers::check_response_status;
-use super::types::{Command, UnexpectedResponse};
+use super::types::{Capabilities, Command, UnexpectedResponse};
 
-const SUPPORTED_CAPABILITIES_VERSION: u8 = 6;
+pub const SUPPORTED_CAPABILITIES_VERSION: u8 = 6;
 
 pub fn connect(path: &str) -> Result<Box<dyn serialport::SerialPort>, Box<dyn std::error::Error>> {
     // connect to the proxmark
@@ -33,21 +33,69 @@ pub fn ping(port: &mut Box<dyn serialport::SerialPort>) -> Result<(), Box<dyn st
     return Ok(());
 }
 
-pub fn check_capabilities(
+/// Reads the capabilities of the firmware, without checking if we support them.
+pub fn get_capabilities(
     port: &mut Box<dyn serialport::SerialPort>,
-) -> Result<(), Box<dyn std::error::Error>> {
+) -> Result<Capabilities, Box<dyn std::error::Error>> {
     let response = send_and_get_command(port, Command::Capabilities, &vec![], true)?;
 
     check_response_status(response.status)?;
-    if response.data[0] != SUPPORTED_CAPABILITIES_VERSION {
+    if response.data.len() < 9 {
+        return Err(Box::new(UnexpectedResponse {
+            additional_text: format!(
+                "Capabilities response ({:02x?}) is too short.",
+                &response.data
+            ),
+        }));
+    }
+    return Ok(Capabilities {
+        version: response.data[0],
+        baudrate: u32::from_le_bytes(response.data[1..5].try_into().unwrap()),
+        bigbuf_size: u32::from_le_bytes(response.data[5..9].try_into().unwrap()),
+    });
+}
+
+pub fn check_capabilities(
+    port: &mut Box<dyn serialport::SerialPort>,
+) -> Result<Capabilities, Box<dyn std::error::Error>> {
+    let capabilities = get_capabilities(port)?;
+    if capabilities.version != SUPPORTED_CAPABILITIES_VERSION {
         return Err(Box::new(UnexpectedResponse {
             additional_text: format!(
                 "Supported capabilities ({}) does not match our supported version ({}).",
-                response.data[0], SUPPORTED_CAPABILITIES_VERSION
+                capabilities.version, SUPPORTED_CAPABILITIES_VERSION
             ),
         }));
     }
-    return Ok(());
+    return Ok(capabilities);
+}
+
+/// Reads the firmware version, like "Iceman/master/v4.19552 2024-11-22 11:04:35 f8d3e8d68".
+pub fn get_version(
+    port: &mut Box<dyn serialport::SerialPort>,
+) -> Result<String, Box<dyn std::error::Error>> {
+    let response = send_and_get_command(port, Command::Version, &vec![], true)?;
+
+    check_response_status(response.status)?;
+    // Chip ID (u32), flash section size (u32), version string length (u32), version string
+    if response.data.len() < 12 {
+        return Err(Box::new(UnexpectedResponse {
+            additional_text: format!("Version response ({:02x?}) is too short.", &response.data),
+        }));
+    }
+    let version_string_data = response.data[12..]
+        .split(|byte| *byte == 0)
+        .next()
+        .unwrap_or(&[]);
+    let version_string = String::from_utf8_lossy(version_string_data);
+    // The version string has a line for the bootrom and one for the OS (like "  os: ..."), we want the latter.
+    let version = version_string
+        .lines()
+        .find_map(|line| line.trim().strip_prefix("os:"))
+        .or(version_string.lines().find(|line| !line.trim().is_empty()))
+        .unwrap_or("")
+        .trim();
+    return Ok(version.to_string());
 }
 
 pub fn quit_session(
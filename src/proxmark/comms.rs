@@ -14,6 +14,9 @@ use std::time::Duration;
 const CMD_MAX_DATA_SIZE: usize = 512;
 const CMD_MAX_FRAME_SIZE: usize = 544;
 const PM3_BAUD: u32 = 115_200;
+// USB IDs of proxmarks running the Iceman firmware
+const PM3_USB_VID: u16 = 0x9ac4;
+const PM3_USB_PID: u16 = 0x4b8f;
 const COMMANDNG_PREAMBLE_MAGIC: u32 = 0x61334d50; // PM3a
 const RESPONSENG_PREAMBLE_MAGIC: u32 = 0x62334d50; // PM3b
 const COMMANDNG_POSTAMBLE_MAGIC: u16 = 0x3361; // a3
@@ -21,20 +24,29 @@ const RESPONSENG_POSTAMBLE_MAGIC: u16 = 0x3362; // b3
 
 /// Finds a proxmark connected via USB.
 pub fn find_proxmark_serial_port() -> Option<String> {
-    let ports = serialport::available_ports().ok()?;
+    return find_proxmark_serial_ports().into_iter().next();
+}
+
+/// Finds all proxmarks connected via USB.
+pub fn find_proxmark_serial_ports() -> Vec<String> {
+    let ports = match serialport::available_ports() {
+        Ok(ports) => ports,
+        Err(_) => return vec![],
+    };
+    let mut proxmark_ports: Vec<String> = vec![];
     for p in ports {
         // p.port_name
         match p.port_type {
             SerialPortType::UsbPort(usb_port_info) => {
-                if usb_port_info.vid == 0x9ac4 && usb_port_info.pid == 0x4b8f {
-                    return Some(p.port_name);
+                if usb_port_info.vid == PM3_USB_VID && usb_port_info.pid == PM3_USB_PID {
+                    proxmark_ports.push(p.port_name);
                 }
             }
             // unknown may be valuable?
             _ => {}
         }
     }
-    return None;
+    return proxmark_ports;
 }
 
 pub fn open_serial_comms(
@@ -6,7 +6,7 @@ pub mod iso14b_commands;
 pub mod types;
 
 pub use self::base_commands::*;
-pub use self::comms::find_proxmark_serial_port;
+pub use self::comms::{find_proxmark_serial_port, find_proxmark_serial_ports, open_serial_comms};
 pub use self::iso14a_commands::*;
 pub use self::iso14b_commands::*;
 pub use self::types::*;
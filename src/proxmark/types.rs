@@ -2,6 +2,15 @@ use serde::{Deserialize, Serialize};
 use std::{error::Error, fmt};
 use strum::{FromRepr, IntoStaticStr};
 
+/// Capabilities the firmware reports, see capabilities_t in the Iceman firmware.
+#[derive(Debug, Clone, Copy)]
+pub struct Capabilities {
+    /// Version of the capabilities structure, which changes along with the firmware
+    pub version: u8,
+    pub baudrate: u32,
+    pub bigbuf_size: u32,
+}
+
 #[derive(Debug)]
 pub struct CannotSelectError;
 
@@ -134,6 +143,7 @@ pub enum Status {
 #[derive(Debug, FromRepr, IntoStaticStr, PartialEq, Clone, Copy)]
 pub enum Command {
     DebugPrintString = 0x0100,
+    Version = 0x0107,
     Ping = 0x0109,
     Capabilities = 0x0112,
     QuitSession = 0x0113,
@@ -1,9 +1,9 @@
 #[cfg(feature = "pcsc")]
-use pcsc::{Context, Scope};
+use pcsc::{Context, ReaderState, Scope, State};
 #[cfg(feature = "proxmark")]
 use serialport::SerialPort;
 use simplelog::{debug, error, info, warn};
-use std::{ffi::CString, fmt, str::FromStr};
+use std::{ffi::CString, fmt, str::FromStr, thread, time::Duration};
 use strum::IntoStaticStr;
 
 use crate::{proxmark, types};
@@ -57,12 +57,63 @@ impl ReaderInterface {
             }
         };
     }
+    /// Returns the readers available through this backend.
+    pub fn list_readers(&self) -> Vec<ReaderInfo> {
+        match self {
+            ReaderInterface::Proxmark => {
+                if !cfg!(feature = "proxmark") {
+                    error!("Cannot list Proxmarks, feature was disabled at compile-time.");
+                    return vec![];
+                }
+
+                return ProxmarkInterface::list_readers();
+            }
+            ReaderInterface::PCSC => {
+                if !cfg!(feature = "pcsc") {
+                    error!("Cannot list PCSC readers, feature was disabled at compile-time.");
+                    return vec![];
+                }
+
+                return PCSCInterface::list_readers();
+            }
+        };
+    }
+}
+
+/// Returns the readers available through all backends that were enabled at compile-time.
+pub fn list_all_readers() -> Vec<ReaderInfo> {
+    let mut readers: Vec<ReaderInfo> = vec![];
+    if cfg!(feature = "pcsc") {
+        readers.extend(ReaderInterface::PCSC.list_readers());
+    }
+    if cfg!(feature = "proxmark") {
+        readers.extend(ReaderInterface::Proxmark.list_readers());
+    }
+    return readers;
+}
+
+/// A reader found while listing readers.
+#[derive(Debug, Clone)]
+pub struct ReaderInfo {
+    /// Name (PCSC) or path (Proxmark) of the reader, as taken by --reader
+    pub name: String,
+    pub backend: ReaderInterface,
+    /// Whether a card is on the reader, None if we couldn't check
+    pub card_present: Option<bool>,
+    /// ATR of the card on the reader, if the reader gives us one
+    pub atr: Option<Vec<u8>>,
+    /// Proxmark capabilities version, which changes along with the firmware
+    pub capabilities_version: Option<u8>,
+    /// Proxmark firmware version
+    pub firmware_version: Option<String>,
 }
 
 #[allow(drop_bounds, dead_code)]
 pub trait Smartcard: Drop {
     fn exchange_command(&mut self, data: &Vec<u8>) -> Option<Vec<u8>>;
     fn exchange_apdu(&mut self, data: &Vec<u8>) -> Option<Vec<u8>>;
+    /// Whether APDUs with extended Lc and Le fields can be sent through this reader
+    fn supports_extended_length(&self) -> bool;
 }
 
 #[allow(drop_bounds)]
@@ -71,6 +122,10 @@ pub trait InterfaceDevice: Drop {
     where
         Self: Sized;
     fn select<'a>(&'a mut self) -> Option<Box<dyn Smartcard + 'a>>;
+    /// Blocks until a card is on the reader, or until there's none if `present` is false.
+    ///
+    /// Returns false if we can't check the reader anymore.
+    fn wait_for_card(&mut self, present: bool) -> bool;
 }
 
 #[cfg(feature = "proxmark")]
@@ -83,6 +138,10 @@ pub struct Proxmark14BSmartcard<'a> {
     interface: &'a mut ProxmarkInterface,
 }
 
+/// How often we check if a card was placed on or removed from a Proxmark, as it has no card events.
+#[cfg(feature = "proxmark")]
+const PROXMARK_CARD_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
 #[cfg(feature = "proxmark")]
 pub struct ProxmarkInterface {
     pub(crate) serial_port: Box<dyn SerialPort>,
@@ -95,6 +154,70 @@ impl Drop for ProxmarkInterface {
     }
 }
 
+#[cfg(feature = "proxmark")]
+impl ProxmarkInterface {
+    /// Returns the proxmarks connected via USB.
+    ///
+    /// This briefly powers the field of each of them, to check if there's a card on it.
+    pub fn list_readers() -> Vec<ReaderInfo> {
+        let mut readers: Vec<ReaderInfo> = vec![];
+        for path in proxmark::find_proxmark_serial_ports() {
+            let mut reader_info = ReaderInfo {
+                name: path.clone(),
+                backend: ReaderInterface::Proxmark,
+                card_present: None,
+                atr: None,
+                capabilities_version: None,
+                firmware_version: None,
+            };
+            let mut port = match proxmark::open_serial_comms(&path) {
+                Ok(port) => port,
+                Err(err) => {
+                    warn!("Couldn't connect to proxmark on {}: {}", path, err);
+                    readers.push(reader_info);
+                    continue;
+                }
+            };
+            // We list proxmarks with firmware we don't support too, so that it's clear what needs updating.
+            match proxmark::ping(&mut port).and_then(|_| proxmark::get_capabilities(&mut port)) {
+                Ok(capabilities) => {
+                    debug!(
+                        "Proxmark on {} has a baudrate of {} and a BigBuf of {}b.",
+                        path, capabilities.baudrate, capabilities.bigbuf_size
+                    );
+                    reader_info.capabilities_version = Some(capabilities.version);
+                    if capabilities.version != proxmark::SUPPORTED_CAPABILITIES_VERSION {
+                        warn!(
+                            "Proxmark on {} has capabilities version {}, but we only support {}. Its firmware may need updating.",
+                            path,
+                            capabilities.version,
+                            proxmark::SUPPORTED_CAPABILITIES_VERSION
+                        );
+                    }
+                    reader_info.firmware_version = match proxmark::get_version(&mut port) {
+                        Ok(version) => Some(version),
+                        Err(err) => {
+                            warn!(
+                                "Couldn't get the firmware version of the proxmark on {}: {}",
+                                path, err
+                            );
+                            None
+                        }
+                    };
+                    reader_info.card_present = Some(
+                        proxmark::select_14a(&mut port, true).is_ok()
+                            || proxmark::select_14b(&mut port, true).is_ok(),
+                    );
+                }
+                Err(err) => warn!("Proxmark on {} didn't respond properly: {}", path, err),
+            }
+            let _ = proxmark::quit_session(&mut port);
+            readers.push(reader_info);
+        }
+        return readers;
+    }
+}
+
 #[cfg(feature = "proxmark")]
 impl InterfaceDevice for ProxmarkInterface {
     fn connect(input_path: Option<&String>) -> Option<Self> {
@@ -142,6 +265,25 @@ impl InterfaceDevice for ProxmarkInterface {
         }
         return None;
     }
+
+    fn wait_for_card(&mut self, present: bool) -> bool {
+        loop {
+            let card_present = proxmark::select_14a(&mut self.serial_port, true).is_ok()
+                || proxmark::select_14b(&mut self.serial_port, true).is_ok();
+            if card_present == present {
+                return true;
+            }
+            // Selecting fails the same way without a card and without a proxmark, so check it's still there.
+            match proxmark::ping(&mut self.serial_port) {
+                Ok(_) => {}
+                Err(err) => {
+                    error!("Lost connection to the proxmark: {}", err);
+                    return false;
+                }
+            }
+            thread::sleep(PROXMARK_CARD_POLL_INTERVAL);
+        }
+    }
 }
 
 #[cfg(feature = "proxmark")]
@@ -166,6 +308,11 @@ impl Smartcard for Proxmark14ASmartcard<'_> {
         let response_without_hash = response.data[0..response.data.len() - 2].to_vec();
         return Some(response_without_hash);
     }
+
+    fn supports_extended_length(&self) -> bool {
+        // Proxmark commands can only carry a bit over 500 bytes.
+        return false;
+    }
 }
 
 #[cfg(feature = "proxmark")]
@@ -191,6 +338,11 @@ impl Smartcard for Proxmark14BSmartcard<'_> {
         let response_without_hash = response.data[0..response.data.len() - 2].to_vec();
         return Some(response_without_hash);
     }
+
+    fn supports_extended_length(&self) -> bool {
+        // Proxmark commands can only carry a bit over 500 bytes.
+        return false;
+    }
 }
 
 #[cfg(feature = "pcsc")]
@@ -206,6 +358,55 @@ impl Drop for PCSCInterface {
     }
 }
 
+#[cfg(feature = "pcsc")]
+impl PCSCInterface {
+    /// Returns the connected PCSC readers.
+    pub fn list_readers() -> Vec<ReaderInfo> {
+        let ctx = match Context::establish(Scope::User) {
+            Ok(ctx) => ctx,
+            Err(err) => {
+                error!("Failed to establish context: {}", err);
+                return vec![];
+            }
+        };
+        let mut readers_buf = [0; 2048];
+        let mut reader_states: Vec<ReaderState> = match ctx.list_readers(&mut readers_buf) {
+            Ok(readers) => readers
+                .map(|reader| ReaderState::new(reader, State::UNAWARE))
+                .collect(),
+            Err(err) => {
+                error!("Failed to list readers: {}", err);
+                return vec![];
+            }
+        };
+        // With UNAWARE states, this returns the current state of every reader right away.
+        let got_status = match ctx.get_status_change(Duration::ZERO, &mut reader_states) {
+            Ok(()) => true,
+            Err(err) => {
+                warn!("Failed to get reader states: {}", err);
+                false
+            }
+        };
+        return reader_states
+            .iter()
+            .map(|reader_state| {
+                let card_present = reader_state.event_state().contains(State::PRESENT);
+                ReaderInfo {
+                    name: reader_state.name().to_string_lossy().to_string(),
+                    backend: ReaderInterface::PCSC,
+                    card_present: got_status.then_some(card_present),
+                    atr: match got_status && card_present {
+                        true => Some(reader_state.atr().to_vec()),
+                        false => None,
+                    },
+                    capabilities_version: None,
+                    firmware_version: None,
+                }
+            })
+            .collect();
+    }
+}
+
 #[cfg(feature = "pcsc")]
 impl InterfaceDevice for PCSCInterface {
     fn connect(input_path: Option<&String>) -> Option<Self> {
@@ -277,6 +478,32 @@ impl InterfaceDevice for PCSCInterface {
             }
         };
     }
+
+    fn wait_for_card(&mut self, present: bool) -> bool {
+        let mut reader_states = vec![ReaderState::new(
+            CString::new(self.reader_name.clone()).unwrap(),
+            State::UNAWARE,
+        )];
+        loop {
+            // Without a timeout, this blocks until the state differs from the current state we pass.
+            match self.context.get_status_change(None, &mut reader_states) {
+                Ok(()) => {}
+                Err(err) => {
+                    error!("Failed to get reader state: {}", err);
+                    return false;
+                }
+            }
+            let event_state = reader_states[0].event_state();
+            if event_state.intersects(State::UNKNOWN | State::UNAVAILABLE) {
+                error!("PCSC reader {:?} is gone.", self.reader_name);
+                return false;
+            }
+            if event_state.contains(State::PRESENT) == present {
+                return true;
+            }
+            reader_states[0].sync_current_state();
+        }
+    }
 }
 
 #[cfg(feature = "pcsc")]
@@ -295,7 +522,7 @@ impl Drop for PCSCSmartcard {
 impl Smartcard for PCSCSmartcard {
     fn exchange_apdu(&mut self, data: &Vec<u8>) -> Option<Vec<u8>> {
         debug!("Sending APDU: {:0x?}", data);
-        let mut rapdu_buf = [0; pcsc::MAX_BUFFER_SIZE];
+        let mut rapdu_buf = vec![0; pcsc::MAX_BUFFER_SIZE_EXTENDED];
         let rapdu = match self.card.transmit(data, &mut rapdu_buf) {
             Ok(rapdu) => rapdu,
             Err(err) => {
@@ -311,4 +538,10 @@ impl Smartcard for PCSCSmartcard {
     fn exchange_command(&mut self, data: &Vec<u8>) -> Option<Vec<u8>> {
         return self.exchange_apdu(data);
     }
+
+    fn supports_extended_length(&self) -> bool {
+        // PC/SC passes APDUs through as-is, but not every reader handles extended ones.
+        // So they're only used if the user asks for them with --extended-length.
+        return true;
+    }
 }
@@ -0,0 +1,147 @@
+//! Trust store for signer certificates, used to verify signatures.
+use p256::ecdsa::signature::Verifier;
+use simplelog::{debug, warn};
+use std::{fs, io, path::Path};
+use x509_cert::{
+    der::{asn1::ObjectIdentifier, Decode, DecodePem},
+    Certificate,
+};
+
+use crate::types::VerificationError;
+
+// RFC 5480, 2.1.1.1
+const OID_SECP256R1: ObjectIdentifier = ObjectIdentifier::new_unwrap("1.2.840.10045.3.1.7");
+const OID_SECP384R1: ObjectIdentifier = ObjectIdentifier::new_unwrap("1.3.132.0.34");
+const OID_SECP521R1: ObjectIdentifier = ObjectIdentifier::new_unwrap("1.3.132.0.35");
+
+pub struct TrustStore {
+    pub certificates: Vec<Certificate>,
+}
+
+impl TrustStore {
+    /// Loads all certificates (DER or PEM) in the given directory.
+    ///
+    /// Files that can't be parsed as certificates are skipped.
+    pub fn load(path: &Path) -> Result<TrustStore, io::Error> {
+        let mut certificates: Vec<Certificate> = vec![];
+        for entry in fs::read_dir(path)? {
+            let file_path = entry?.path();
+            if !file_path.is_file() {
+                continue;
+            }
+            let file_data = fs::read(&file_path)?;
+            let certificate = match Certificate::from_der(&file_data) {
+                Ok(certificate) => certificate,
+                Err(_) => match Certificate::from_pem(&file_data) {
+                    Ok(certificate) => certificate,
+                    Err(_) => {
+                        warn!(
+                            "Skipping {}, it's not a DER or PEM certificate.",
+                            file_path.to_string_lossy()
+                        );
+                        continue;
+                    }
+                },
+            };
+            debug!(
+                "Loaded certificate {} from {}",
+                certificate.tbs_certificate.subject,
+                file_path.to_string_lossy()
+            );
+            certificates.push(certificate);
+        }
+        return Ok(TrustStore { certificates });
+    }
+
+    /// Finds a certificate by the country in its subject and its serial number (as hex).
+    ///
+    /// Serial numbers are only unique per issuer, so certificates of other countries never match.
+    pub fn find_certificate(
+        &self,
+        country_code: &str,
+        serial_number: &str,
+    ) -> Option<&Certificate> {
+        let country_code = country_code.to_uppercase();
+        let serial_number = serial_number.trim_start_matches('0').to_uppercase();
+        return self.certificates.iter().find(|certificate| {
+            let certificate_serial = certificate
+                .tbs_certificate
+                .serial_number
+                .as_bytes()
+                .iter()
+                .map(|byte| format!("{:02X}", byte))
+                .collect::<String>();
+            certificate_serial.trim_start_matches('0') == serial_number
+                && certificate_country(certificate).as_deref() == Some(country_code.as_str())
+        });
+    }
+}
+
+/// Returns the country (C=) in the subject of a certificate.
+pub fn certificate_country(certificate: &Certificate) -> Option<String> {
+    return certificate
+        .tbs_certificate
+        .subject
+        .to_string()
+        .split(',')
+        .find_map(|rdn| rdn.strip_prefix("C="))
+        .map(|country_code| country_code.to_uppercase());
+}
+
+/// Verifies a plain (r || s) ECDSA signature over data with the public key of the given certificate.
+///
+/// The hash algorithm is picked based on the curve, as done by ICAO 9303 part 13 and BSI TR-03137.
+pub fn verify_ecdsa_signature(
+    certificate: &Certificate,
+    data: &[u8],
+    signature: &[u8],
+) -> Result<(), VerificationError> {
+    let public_key_info = &certificate.tbs_certificate.subject_public_key_info;
+    let public_key = public_key_info.subject_public_key.raw_bytes();
+    let curve_oid: ObjectIdentifier = match public_key_info.algorithm.parameters.as_ref() {
+        Some(parameters) => parameters.decode_as().map_err(|_| VerificationError {
+            additional_text: "Certificate uses explicit curve parameters, which aren't supported."
+                .to_string(),
+        })?,
+        None => {
+            return Err(VerificationError {
+                additional_text: "Certificate public key has no curve parameters.".to_string(),
+            });
+        }
+    };
+
+    let verification_result = match curve_oid {
+        OID_SECP256R1 => {
+            let verifying_key = p256::ecdsa::VerifyingKey::from_sec1_bytes(public_key);
+            let signature = p256::ecdsa::Signature::from_slice(signature);
+            match (verifying_key, signature) {
+                (Ok(verifying_key), Ok(signature)) => verifying_key.verify(data, &signature),
+                _ => Err(p256::ecdsa::Error::new()),
+            }
+        }
+        OID_SECP384R1 => {
+            let verifying_key = p384::ecdsa::VerifyingKey::from_sec1_bytes(public_key);
+            let signature = p384::ecdsa::Signature::from_slice(signature);
+            match (verifying_key, signature) {
+                (Ok(verifying_key), Ok(signature)) => verifying_key.verify(data, &signature),
+                _ => Err(p384::ecdsa::Error::new()),
+            }
+        }
+        OID_SECP521R1 => {
+            let verifying_key = p521::ecdsa::VerifyingKey::from_sec1_bytes(public_key);
+            let signature = p521::ecdsa::Signature::from_slice(signature);
+            match (verifying_key, signature) {
+                (Ok(verifying_key), Ok(signature)) => verifying_key.verify(data, &signature),
+                _ => Err(p521::ecdsa::Error::new()),
+            }
+        }
+        _ => {
+            return Err(VerificationError {
+                additional_text: format!("Unsupported curve: {}", curve_oid),
+            });
+        }
+    };
+    return verification_result.map_err(|_| VerificationError {
+        additional_text: "Signature does not match.".to_string(),
+    });
+}
@@ -0,0 +1,457 @@
+use crate::dg_parsers::helpers as dg_helpers;
+use crate::types::{ParsedDataGroup, EFDG1, EFDG11, EFDG12, MRZ};
+
+const PRINTED_MRZ_VS_DG1: &str = "Printed MRZ / EF.DG1";
+const DG1_VS_DG11: &str = "EF.DG1 / EF.DG11";
+const DG1_VS_DG12: &str = "EF.DG1 / EF.DG12";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ConsistencyFindingSeverity {
+    /// Values couldn't be compared, or are unusual without contradicting each other.
+    Notice,
+    /// Values contradict each other.
+    Mismatch,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConsistencyFinding {
+    pub severity: ConsistencyFindingSeverity,
+    /// Check that produced this finding, like "Printed MRZ / EF.DG1"
+    pub check: &'static str,
+    pub field: String,
+    /// Value from the first source of the check
+    pub first_value: String,
+    /// Value from the second source of the check
+    pub second_value: String,
+    pub note: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct ConsistencyReport {
+    /// Checks that had enough data to be performed.
+    pub checks_performed: Vec<&'static str>,
+    pub findings: Vec<ConsistencyFinding>,
+}
+
+/// Normalizes a name from an MRZ or DG11 for comparison.
+///
+/// Fillers and separators become single spaces, so "DOE<<JOHN" and "DOE, JOHN" match.
+/// The MRZ writes hyphens as fillers and drops apostrophes (ICAO 9303 part 3, 4.6), so "SMITH-JONES" and "O'NEIL" match too.
+fn normalize_name(text: &str) -> String {
+    return text
+        .replace('\'', "")
+        .replace(['<', ',', '-'], " ")
+        .split_whitespace()
+        .collect::<Vec<&str>>()
+        .join(" ")
+        .to_uppercase();
+}
+
+/// Returns the length of the name field of the given MRZ format.
+fn mrz_name_field_len(mrz: &MRZ) -> usize {
+    return match mrz {
+        MRZ::TD1(_) => 30,
+        MRZ::TD2(_) | MRZ::MRVB(_) => 31,
+        MRZ::TD3(_) | MRZ::MRVA(_) => 39,
+    };
+}
+
+impl ConsistencyReport {
+    /// Cross-checks the supplied printed MRZ (if any) against DG1, and DG1 against DG11 and DG12.
+    ///
+    /// Checks are skipped if the data groups they need weren't read or parsed.
+    pub fn check(printed_mrz: Option<&MRZ>, parsed_data_groups: &[ParsedDataGroup]) -> Self {
+        let mut dg1: Option<&EFDG1> = None;
+        let mut dg11: Option<&EFDG11> = None;
+        let mut dg12: Option<&EFDG12> = None;
+        for parsed_data_group in parsed_data_groups.iter() {
+            match parsed_data_group {
+                ParsedDataGroup::EFDG1(file) => dg1 = Some(file),
+                ParsedDataGroup::EFDG11(file) => dg11 = Some(file),
+                ParsedDataGroup::EFDG12(file) => dg12 = Some(file),
+                _ => {}
+            }
+        }
+
+        let mut report = ConsistencyReport::default();
+        let dg1 = match dg1 {
+            Some(dg1) => dg1,
+            None => return report,
+        };
+        match printed_mrz {
+            Some(printed_mrz) => report.check_printed_mrz_against_dg1(printed_mrz, dg1),
+            None => {}
+        }
+        match dg11 {
+            Some(dg11) => report.check_dg1_against_dg11(dg1, dg11),
+            None => {}
+        }
+        match dg12 {
+            Some(dg12) => report.check_dg1_against_dg12(dg1, dg12),
+            None => {}
+        }
+        return report;
+    }
+
+    /// Returns true if any of the findings is a mismatch.
+    pub fn has_mismatches(&self) -> bool {
+        return self
+            .findings
+            .iter()
+            .any(|finding| finding.severity == ConsistencyFindingSeverity::Mismatch);
+    }
+
+    fn add_finding(
+        &mut self,
+        severity: ConsistencyFindingSeverity,
+        check: &'static str,
+        field: &str,
+        first_value: &str,
+        second_value: &str,
+        note: Option<&str>,
+    ) {
+        self.findings.push(ConsistencyFinding {
+            severity: severity,
+            check: check,
+            field: field.to_string(),
+            first_value: first_value.to_string(),
+            second_value: second_value.to_string(),
+            note: note.map(|note| note.to_string()),
+        });
+    }
+
+    fn check_printed_mrz_against_dg1(&mut self, printed_mrz: &MRZ, dg1: &EFDG1) {
+        self.checks_performed.push(PRINTED_MRZ_VS_DG1);
+        if printed_mrz.format_name() != dg1.mrz.format_name() {
+            self.add_finding(
+                ConsistencyFindingSeverity::Mismatch,
+                PRINTED_MRZ_VS_DG1,
+                "MRZ format",
+                printed_mrz.format_name(),
+                dg1.mrz.format_name(),
+                None,
+            );
+            return;
+        }
+
+        let mut fields_match = true;
+        for ((field, printed_value), (_, chip_value)) in
+            printed_mrz.fields().iter().zip(dg1.mrz.fields().iter())
+        {
+            if printed_value != chip_value {
+                fields_match = false;
+                self.add_finding(
+                    ConsistencyFindingSeverity::Mismatch,
+                    PRINTED_MRZ_VS_DG1,
+                    field,
+                    printed_value,
+                    chip_value,
+                    None,
+                );
+            }
+        }
+
+        // Fields can be identical while check digits or fillers differ.
+        let (printed_raw, chip_raw) = match (printed_mrz, &dg1.mrz) {
+            (MRZ::TD1(printed), MRZ::TD1(chip)) => (&printed.raw_mrz, &chip.raw_mrz),
+            (MRZ::TD2(printed), MRZ::TD2(chip)) => (&printed.raw_mrz, &chip.raw_mrz),
+            (MRZ::TD3(printed), MRZ::TD3(chip)) => (&printed.raw_mrz, &chip.raw_mrz),
+            (MRZ::MRVA(printed), MRZ::MRVA(chip)) | (MRZ::MRVB(printed), MRZ::MRVB(chip)) => {
+                (&printed.raw_mrz, &chip.raw_mrz)
+            }
+            _ => return,
+        };
+        if fields_match && printed_raw != chip_raw {
+            self.add_finding(
+                ConsistencyFindingSeverity::Mismatch,
+                PRINTED_MRZ_VS_DG1,
+                "Raw MRZ",
+                printed_raw,
+                chip_raw,
+                Some("Fields match, but check digits or fillers differ."),
+            );
+        }
+    }
+
+    fn check_dg1_against_dg11(&mut self, dg1: &EFDG1, dg11: &EFDG11) {
+        self.checks_performed.push(DG1_VS_DG11);
+
+        match dg11.full_name.as_ref() {
+            Some(full_name) => {
+                let mrz_name = normalize_name(dg1.mrz.name_of_holder());
+                let dg11_name = normalize_name(full_name);
+                // The MRZ name is truncated if it fills the whole field (ICAO 9303 part 3, 4.6.1).
+                let mrz_name_truncated =
+                    dg1.mrz.name_of_holder().len() >= mrz_name_field_len(&dg1.mrz);
+                if mrz_name == dg11_name || (mrz_name_truncated && dg11_name.starts_with(&mrz_name))
+                {
+                    // Consistent
+                } else if !full_name.is_ascii() {
+                    self.add_finding(
+                        ConsistencyFindingSeverity::Notice,
+                        DG1_VS_DG11,
+                        "Name of holder",
+                        dg1.mrz.name_of_holder(),
+                        full_name,
+                        Some("DG11 name isn't transliterated, compare manually."),
+                    );
+                } else if mrz_name_truncated {
+                    self.add_finding(
+                        ConsistencyFindingSeverity::Notice,
+                        DG1_VS_DG11,
+                        "Name of holder",
+                        dg1.mrz.name_of_holder(),
+                        full_name,
+                        Some("MRZ name is truncated, compare manually."),
+                    );
+                } else {
+                    self.add_finding(
+                        ConsistencyFindingSeverity::Mismatch,
+                        DG1_VS_DG11,
+                        "Name of holder",
+                        dg1.mrz.name_of_holder(),
+                        full_name,
+                        None,
+                    );
+                }
+            }
+            None => {}
+        }
+
+        match dg11.full_date_of_birth.as_ref() {
+            Some(full_date_of_birth) => {
+                let mrz_date_of_birth = dg1.mrz.date_of_birth();
+                if full_date_of_birth.len() != 8 || !full_date_of_birth.is_ascii() {
+                    self.add_finding(
+                        ConsistencyFindingSeverity::Notice,
+                        DG1_VS_DG11,
+                        "Date of birth",
+                        mrz_date_of_birth,
+                        full_date_of_birth,
+                        Some("DG11 date of birth isn't in YYYYMMDD format."),
+                    );
+                    return;
+                }
+                // Unknown parts of a date of birth are filled with < in the MRZ, so those are skipped.
+                let dates_match = mrz_date_of_birth
+                    .chars()
+                    .zip(full_date_of_birth[2..].chars())
+                    .all(|(mrz_char, dg11_char)| {
+                        mrz_char == '<' || dg11_char == '<' || mrz_char == dg11_char
+                    });
+                if !dates_match {
+                    self.add_finding(
+                        ConsistencyFindingSeverity::Mismatch,
+                        DG1_VS_DG11,
+                        "Date of birth",
+                        mrz_date_of_birth,
+                        full_date_of_birth,
+                        None,
+                    );
+                }
+            }
+            None => {}
+        }
+    }
+
+    fn check_dg1_against_dg12(&mut self, dg1: &EFDG1, dg12: &EFDG12) {
+        let date_of_issue = match dg12.date_of_issue.as_ref() {
+            Some(date_of_issue) => date_of_issue,
+            None => return,
+        };
+        self.checks_performed.push(DG1_VS_DG12);
+        let mrz_date_of_expiry = dg1.mrz.date_of_expiry();
+
+        let (issue_dd, issue_mm, issue_yyyy, expiry_dd, expiry_mm, expiry_yyyy) = match (
+            dg_helpers::parse_dg_date(date_of_issue),
+            dg_helpers::parse_mrz_date(mrz_date_of_expiry),
+        ) {
+            (Some((issue_dd, issue_mm, issue_yyyy)), Some((expiry_dd, expiry_mm, expiry_yyyy))) => {
+                (
+                    issue_dd,
+                    issue_mm,
+                    issue_yyyy,
+                    expiry_dd,
+                    expiry_mm,
+                    expiry_yyyy,
+                )
+            }
+            _ => {
+                self.add_finding(
+                    ConsistencyFindingSeverity::Notice,
+                    DG1_VS_DG12,
+                    "Date of expiry / Date of issue",
+                    mrz_date_of_expiry,
+                    date_of_issue,
+                    Some("Couldn't parse the dates."),
+                );
+                return;
+            }
+        };
+
+        // Tuples compare element by element, so these sort by year, then month, then day.
+        let date_of_issue_sortable = (issue_yyyy, issue_mm, issue_dd);
+        let date_of_expiry_sortable = (expiry_yyyy, expiry_mm, expiry_dd);
+        if date_of_issue_sortable >= date_of_expiry_sortable {
+            self.add_finding(
+                ConsistencyFindingSeverity::Mismatch,
+                DG1_VS_DG12,
+                "Date of expiry / Date of issue",
+                mrz_date_of_expiry,
+                date_of_issue,
+                Some("Document was issued after it expired."),
+            );
+        } else if (issue_yyyy + 10, issue_mm, issue_dd) < date_of_expiry_sortable {
+            self.add_finding(
+                ConsistencyFindingSeverity::Notice,
+                DG1_VS_DG12,
+                "Date of expiry / Date of issue",
+                mrz_date_of_expiry,
+                date_of_issue,
+                Some("Document is valid for more than 10 years, which is unusual."),
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TD3_MRZ: &str =
+        "P<UTOERIKSSON<<ANNA<MARIA<<<<<<<<<<<<<<<<<<<L898902C36UTO7408122F1204159ZE184226B<<<<<10";
+
+    fn dg11_with(full_name: &str, full_date_of_birth: &str) -> EFDG11 {
+        return EFDG11 {
+            full_name: Some(full_name.to_string()),
+            other_names: None,
+            personal_number: None,
+            full_date_of_birth: Some(full_date_of_birth.to_string()),
+            place_of_birth: None,
+            permanent_address: None,
+            telephone: None,
+            profession: None,
+            title: None,
+            personal_summary: None,
+            proof_of_citizenship: None,
+            other_valid_td_numbers: None,
+            custody_information: None,
+        };
+    }
+
+    fn dg12_with(date_of_issue: &str) -> EFDG12 {
+        return EFDG12 {
+            issuing_authority: None,
+            date_of_issue: Some(date_of_issue.to_string()),
+            other_persons: None,
+            endorsements_observations: None,
+            tax_exit_requirements: None,
+            image_of_front_of_emrtd: None,
+            image_of_rear_of_emrtd: None,
+            personalization_timestamp: None,
+            personalization_device_serial_number: None,
+        };
+    }
+
+    #[test]
+    fn consistent_documents_have_no_findings() {
+        let mrz = MRZ::deserialize(&TD3_MRZ.to_string()).unwrap();
+        let parsed_data_groups = vec![
+            ParsedDataGroup::EFDG1(EFDG1 {
+                mrz: MRZ::deserialize(&TD3_MRZ.to_string()).unwrap(),
+            }),
+            ParsedDataGroup::EFDG11(dg11_with("ERIKSSON<<ANNA<MARIA", "19740812")),
+        ];
+        let report = ConsistencyReport::check(Some(&mrz), &parsed_data_groups);
+        assert_eq!(
+            report.checks_performed,
+            vec![PRINTED_MRZ_VS_DG1, DG1_VS_DG11]
+        );
+        assert_eq!(report.findings, vec![]);
+    }
+
+    #[test]
+    fn mismatching_documents_are_reported() {
+        let printed_mrz = MRZ::deserialize(
+            &"P<UTOERIKSSON<<ANNA<MARIA<<<<<<<<<<<<<<<<<<<L898902C36UTO7408122F1304159ZE184226B<<<<<10"
+                .to_string(),
+        )
+        .unwrap();
+        let parsed_data_groups = vec![
+            ParsedDataGroup::EFDG1(EFDG1 {
+                mrz: MRZ::deserialize(&TD3_MRZ.to_string()).unwrap(),
+            }),
+            ParsedDataGroup::EFDG11(dg11_with("ERIKSSON<<JOHN", "19750812")),
+        ];
+        let report = ConsistencyReport::check(Some(&printed_mrz), &parsed_data_groups);
+        assert!(report.has_mismatches());
+        let mismatching_fields: Vec<&str> = report
+            .findings
+            .iter()
+            .map(|finding| finding.field.as_str())
+            .collect();
+        assert_eq!(
+            mismatching_fields,
+            vec!["Date of expiry", "Name of holder", "Date of birth"]
+        );
+    }
+
+    #[test]
+    fn dg12_date_of_issue_is_checked_against_expiry() {
+        // TD3_MRZ expires on 2012-04-15, so issuing it ten years before is fine.
+        let dg1 = ParsedDataGroup::EFDG1(EFDG1 {
+            mrz: MRZ::deserialize(&TD3_MRZ.to_string()).unwrap(),
+        });
+        let parsed_data_groups = vec![dg1, ParsedDataGroup::EFDG12(dg12_with("20020415"))];
+        let report = ConsistencyReport::check(None, &parsed_data_groups);
+        assert_eq!(report.checks_performed, vec![DG1_VS_DG12]);
+        assert_eq!(report.findings, vec![]);
+
+        let dg1 = ParsedDataGroup::EFDG1(EFDG1 {
+            mrz: MRZ::deserialize(&TD3_MRZ.to_string()).unwrap(),
+        });
+        let parsed_data_groups = vec![dg1, ParsedDataGroup::EFDG12(dg12_with("20130101"))];
+        let report = ConsistencyReport::check(None, &parsed_data_groups);
+        assert!(report.has_mismatches());
+        assert_eq!(report.findings.len(), 1);
+        assert_eq!(report.findings[0].check, DG1_VS_DG12);
+        assert_eq!(report.findings[0].second_value, "20130101");
+    }
+
+    #[test]
+    fn hyphens_and_apostrophes_in_names_are_normalized() {
+        for (mrz_name, dg11_name) in [
+            ("SMITH<JONES<<ANNE", "SMITH-JONES<<ANNE"),
+            ("ONEIL<<SEAN", "O'NEIL<<SEAN"),
+        ] {
+            let mrz = format!("P<UTO{:<<39}{}", mrz_name, &TD3_MRZ[44..]);
+            let parsed_data_groups = vec![
+                ParsedDataGroup::EFDG1(EFDG1 {
+                    mrz: MRZ::deserialize(&mrz).unwrap(),
+                }),
+                ParsedDataGroup::EFDG11(dg11_with(dg11_name, "19740812")),
+            ];
+            let report = ConsistencyReport::check(None, &parsed_data_groups);
+            assert_eq!(report.checks_performed, vec![DG1_VS_DG11]);
+            assert_eq!(report.findings, vec![]);
+        }
+    }
+
+    #[test]
+    fn non_ascii_dg11_date_of_birth_is_a_notice() {
+        let parsed_data_groups = vec![
+            ParsedDataGroup::EFDG1(EFDG1 {
+                mrz: MRZ::deserialize(&TD3_MRZ.to_string()).unwrap(),
+            }),
+            // 8 bytes, with the second character cut in half when skipping the century
+            ParsedDataGroup::EFDG11(dg11_with("ERIKSSON<<ANNA<MARIA", "1É40812")),
+        ];
+        let report = ConsistencyReport::check(None, &parsed_data_groups);
+        assert!(!report.has_mismatches());
+        assert_eq!(report.findings.len(), 1);
+        assert_eq!(
+            report.findings[0].severity,
+            ConsistencyFindingSeverity::Notice
+        );
+    }
+}
@@ -6,6 +6,7 @@ pub static DATA_GROUPS: [DataGroup; 22] = [
         tag: 0x60,
         dg_num: 0,
         file_id: 0x011E,
+        sfi: Some(0x1E),
         description: "Header and Data Group Presence Information",
         pace_only: false,
         eac_only: false,
@@ -13,12 +14,14 @@ pub static DATA_GROUPS: [DataGroup; 22] = [
         parser: dg_parsers::ef_com::parser,
         dumper: dg_parsers::generic::dumper,
         is_binary: false,
+        is_asn1: true,
     },
     DataGroup {
         name: "EF.CardAccess",
         tag: 0xff,
         dg_num: 0,
         file_id: 0x011C,
+        sfi: Some(0x1C),
         description: "SecurityInfos (PACE)",
         pace_only: true,
         eac_only: false,
@@ -26,12 +29,14 @@ pub static DATA_GROUPS: [DataGroup; 22] = [
         parser: dg_parsers::generic::parser,
         dumper: dg_parsers::generic::dumper,
         is_binary: false,
+        is_asn1: true,
     },
     DataGroup {
         name: "EF.CardSecurity",
         tag: 0xff,
         dg_num: 0,
         file_id: 0x011D,
+        sfi: Some(0x1D),
         description: "SecurityInfos for Chip Authentication Mapping (PACE)",
         pace_only: true,
         eac_only: false,
@@ -39,38 +44,44 @@ pub static DATA_GROUPS: [DataGroup; 22] = [
         parser: dg_parsers::generic::parser,
         dumper: dg_parsers::generic::dumper,
         is_binary: false,
+        is_asn1: true,
     },
     DataGroup {
         name: "EF.ATR/INFO",
         tag: 0xff,
         dg_num: 0,
         file_id: 0x2F01,
+        sfi: Some(0x01),
         description: "Answer to Reset File",
         pace_only: false,
         eac_only: false,
         in_lds1: false,
-        parser: dg_parsers::generic::parser,
+        parser: dg_parsers::ef_atr_info::parser,
         dumper: dg_parsers::generic::dumper,
         is_binary: false,
+        is_asn1: false,
     },
     DataGroup {
         name: "EF.DIR",
         tag: 0xff,
         dg_num: 0,
         file_id: 0x2F00,
+        sfi: Some(0x1E),
         description: "Directory",
         pace_only: false,
         eac_only: false,
         in_lds1: false,
-        parser: dg_parsers::generic::parser,
+        parser: dg_parsers::ef_dir::parser,
         dumper: dg_parsers::generic::dumper,
         is_binary: false,
+        is_asn1: false,
     },
     DataGroup {
         name: "EF.DG1",
         tag: 0x61,
         dg_num: 1,
         file_id: 0x0101,
+        sfi: Some(0x01),
         description: "Details recorded in MRZ",
         pace_only: false,
         eac_only: false,
@@ -78,12 +89,14 @@ pub static DATA_GROUPS: [DataGroup; 22] = [
         parser: dg_parsers::ef_dg1::parser,
         dumper: dg_parsers::generic::dumper,
         is_binary: false,
+        is_asn1: true,
     },
     DataGroup {
         name: "EF.DG2",
         tag: 0x75,
         dg_num: 2,
         file_id: 0x0102,
+        sfi: Some(0x02),
         description: "Encoded Face",
         pace_only: false,
         eac_only: false,
@@ -91,12 +104,14 @@ pub static DATA_GROUPS: [DataGroup; 22] = [
         parser: dg_parsers::ef_dg2_3_4::parser,
         dumper: dg_parsers::ef_dg2_3_4::dumper,
         is_binary: true,
+        is_asn1: true,
     },
     DataGroup {
         name: "EF.DG3",
         tag: 0x63,
         dg_num: 3,
         file_id: 0x0103,
+        sfi: Some(0x03),
         description: "Encoded Finger(s)",
         pace_only: false,
         eac_only: true,
@@ -104,12 +119,14 @@ pub static DATA_GROUPS: [DataGroup; 22] = [
         parser: dg_parsers::ef_dg2_3_4::parser,
         dumper: dg_parsers::ef_dg2_3_4::dumper,
         is_binary: true,
+        is_asn1: true,
     },
     DataGroup {
         name: "EF.DG4",
         tag: 0x76,
         dg_num: 4,
         file_id: 0x0104,
+        sfi: Some(0x04),
         description: "Encoded Eye(s)",
         pace_only: false,
         eac_only: true,
@@ -117,12 +134,14 @@ pub static DATA_GROUPS: [DataGroup; 22] = [
         parser: dg_parsers::ef_dg2_3_4::parser,
         dumper: dg_parsers::ef_dg2_3_4::dumper,
         is_binary: true,
+        is_asn1: true,
     },
     DataGroup {
         name: "EF.DG5",
         tag: 0x65,
         dg_num: 5,
         file_id: 0x0105,
+        sfi: Some(0x05),
         description: "Displayed Portrait",
         pace_only: false,
         eac_only: false,
@@ -130,12 +149,14 @@ pub static DATA_GROUPS: [DataGroup; 22] = [
         parser: dg_parsers::ef_dg5::parser,
         dumper: dg_parsers::ef_dg5::dumper,
         is_binary: true,
+        is_asn1: true,
     },
     DataGroup {
         name: "EF.DG6",
         tag: 0x66,
         dg_num: 6,
         file_id: 0x0106,
+        sfi: Some(0x06),
         description: "Reserved for Future Use",
         pace_only: false,
         eac_only: false,
@@ -143,12 +164,14 @@ pub static DATA_GROUPS: [DataGroup; 22] = [
         parser: dg_parsers::generic::parser,
         dumper: dg_parsers::generic::dumper,
         is_binary: false,
+        is_asn1: true,
     },
     DataGroup {
         name: "EF.DG7",
         tag: 0x67,
         dg_num: 7,
         file_id: 0x0107,
+        sfi: Some(0x07),
         description: "Displayed Signature or Usual Mark",
         pace_only: false,
         eac_only: false,
@@ -156,12 +179,14 @@ pub static DATA_GROUPS: [DataGroup; 22] = [
         parser: dg_parsers::ef_dg7::parser,
         dumper: dg_parsers::ef_dg7::dumper,
         is_binary: false,
+        is_asn1: true,
     },
     DataGroup {
         name: "EF.DG8",
         tag: 0x68,
         dg_num: 8,
         file_id: 0x0108,
+        sfi: Some(0x08),
         description: "Data Feature(s)",
         pace_only: false,
         eac_only: false,
@@ -169,12 +194,14 @@ pub static DATA_GROUPS: [DataGroup; 22] = [
         parser: dg_parsers::generic::parser,
         dumper: dg_parsers::generic::dumper,
         is_binary: false,
+        is_asn1: true,
     },
     DataGroup {
         name: "EF.DG9",
         tag: 0x69,
         dg_num: 9,
         file_id: 0x0109,
+        sfi: Some(0x09),
         description: "Structure Feature(s)",
         pace_only: false,
         eac_only: false,
@@ -182,12 +209,14 @@ pub static DATA_GROUPS: [DataGroup; 22] = [
         parser: dg_parsers::generic::parser,
         dumper: dg_parsers::generic::dumper,
         is_binary: false,
+        is_asn1: true,
     },
     DataGroup {
         name: "EF.DG10",
         tag: 0x6a,
         dg_num: 10,
         file_id: 0x010A,
+        sfi: Some(0x0A),
         description: "Substance Feature(s)",
         pace_only: false,
         eac_only: false,
@@ -195,12 +224,14 @@ pub static DATA_GROUPS: [DataGroup; 22] = [
         parser: dg_parsers::generic::parser,
         dumper: dg_parsers::generic::dumper,
         is_binary: false,
+        is_asn1: true,
     },
     DataGroup {
         name: "EF.DG11",
         tag: 0x6b,
         dg_num: 11,
         file_id: 0x010B,
+        sfi: Some(0x0B),
         description: "Additional Personal Detail(s)",
         pace_only: false,
         eac_only: false,
@@ -208,12 +239,14 @@ pub static DATA_GROUPS: [DataGroup; 22] = [
         parser: dg_parsers::ef_dg11::parser,
         dumper: dg_parsers::generic::dumper,
         is_binary: false,
+        is_asn1: true,
     },
     DataGroup {
         name: "EF.DG12",
         tag: 0x6c,
         dg_num: 12,
         file_id: 0x010C,
+        sfi: Some(0x0C),
         description: "Additional Document Detail(s)",
         pace_only: false,
         eac_only: false,
@@ -221,25 +254,29 @@ pub static DATA_GROUPS: [DataGroup; 22] = [
         parser: dg_parsers::ef_dg12::parser,
         dumper: dg_parsers::generic::dumper,
         is_binary: false,
+        is_asn1: true,
     },
     DataGroup {
         name: "EF.DG13",
         tag: 0x6d,
         dg_num: 13,
         file_id: 0x010D,
+        sfi: Some(0x0D),
         description: "Optional Detail(s)",
         pace_only: false,
         eac_only: false,
         in_lds1: true,
-        parser: dg_parsers::generic::parser,
+        parser: dg_parsers::ef_dg13::parser,
         dumper: dg_parsers::generic::dumper,
         is_binary: false,
+        is_asn1: true,
     },
     DataGroup {
         name: "EF.DG14",
         tag: 0x6e,
         dg_num: 14,
         file_id: 0x010E,
+        sfi: Some(0x0E),
         description: "Security Options",
         pace_only: false,
         eac_only: false,
@@ -247,12 +284,14 @@ pub static DATA_GROUPS: [DataGroup; 22] = [
         parser: dg_parsers::generic::parser,
         dumper: dg_parsers::generic::dumper,
         is_binary: true,
+        is_asn1: true,
     },
     DataGroup {
         name: "EF.DG15",
         tag: 0x6f,
         dg_num: 15,
         file_id: 0x010F,
+        sfi: Some(0x0F),
         description: "Active Authentication Public Key Info",
         pace_only: false,
         eac_only: false,
@@ -260,32 +299,37 @@ pub static DATA_GROUPS: [DataGroup; 22] = [
         parser: dg_parsers::generic::parser,
         dumper: dg_parsers::generic::dumper,
         is_binary: true,
+        is_asn1: true,
     },
     DataGroup {
         name: "EF.DG16",
         tag: 0x70,
         dg_num: 16,
         file_id: 0x0110,
+        sfi: Some(0x10),
         description: "Person(s) to Notify",
         pace_only: false,
         eac_only: false,
         in_lds1: true,
-        parser: dg_parsers::generic::parser,
+        parser: dg_parsers::ef_dg16::parser,
         dumper: dg_parsers::generic::dumper,
         is_binary: false,
+        is_asn1: true,
     },
     DataGroup {
         name: "EF.SOD",
         tag: 0x77,
         dg_num: 0,
         file_id: 0x011D,
+        sfi: Some(0x1D),
         description: "Document Security Object",
         pace_only: false,
         eac_only: false,
         in_lds1: true,
-        parser: dg_parsers::generic::parser,
+        parser: dg_parsers::ef_sod::parser,
         dumper: dg_parsers::generic::dumper,
         is_binary: false,
+        is_asn1: true,
     },
 ];
 
@@ -325,6 +369,9 @@ pub struct DataGroup {
     pub tag: u8,
     pub dg_num: u8,
     pub file_id: u16,
+    // Short File Identifier, which lets us read the file without selecting it first.
+    // See ICAO 9303 p10, section 3.6.2 for the list.
+    pub sfi: Option<u8>,
     pub description: &'static str,
     pub pace_only: bool,
     pub eac_only: bool,
@@ -335,6 +382,9 @@ pub struct DataGroup {
     pub in_lds1: bool,
     // Whether this file should be read when printing info
     pub is_binary: bool,
+    // Whether the file is a single BER-TLV, which lets us get its length from the header.
+    // Others (like EF.ATR/INFO) are read until the chip tells us the file ended.
+    pub is_asn1: bool,
     pub parser: fn(&Vec<u8>, &DataGroup, bool) -> Option<types::ParsedDataGroup>,
     pub dumper: fn(
         &Vec<u8>,
@@ -343,3 +393,72 @@ pub struct DataGroup {
         &String,
     ) -> Result<(), std::io::Error>,
 }
+
+/// Which LDS1 data groups to read, like from `--only` and `--skip`.
+///
+/// EF.COM and the files under the master file are always read, as we need them to read the rest.
+#[derive(Debug, Clone, Default)]
+pub struct DataGroupSelection {
+    /// Only read these data groups, if set
+    pub only: Option<Vec<&'static str>>,
+    /// Don't read these data groups
+    pub skip: Vec<&'static str>,
+}
+
+impl DataGroupSelection {
+    /// Finds an LDS1 data group by name, with or without the "EF." prefix (like "DG1" or "EF.SOD").
+    pub fn parse_name(name: &str) -> Result<&'static str, types::ParseError> {
+        let name = name.trim();
+        return DATA_GROUPS
+            .iter()
+            .find(|data_group| {
+                data_group.in_lds1
+                    && (data_group.name.eq_ignore_ascii_case(name)
+                        || data_group.name[3..].eq_ignore_ascii_case(name))
+            })
+            .map(|data_group| data_group.name)
+            .ok_or(types::ParseError {});
+    }
+
+    /// Whether the data group should be read.
+    ///
+    /// Binary data groups are only read when dumping, unless they were explicitly asked for.
+    pub fn includes(&self, data_group: &DataGroup, read_binary: bool) -> bool {
+        if self.skip.contains(&data_group.name) {
+            return false;
+        }
+        return match self.only.as_ref() {
+            Some(only) => only.contains(&data_group.name),
+            None => read_binary || !data_group.is_binary,
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn data_group_selection() {
+        assert_eq!(DataGroupSelection::parse_name("dg1").unwrap(), "EF.DG1");
+        assert_eq!(DataGroupSelection::parse_name("EF.SOD").unwrap(), "EF.SOD");
+        // Files under the master file can't be selected
+        assert!(DataGroupSelection::parse_name("DIR").is_err());
+
+        let dg1 = &DATA_GROUPS[DataGroupEnum::EFDg1 as usize];
+        let dg2 = &DATA_GROUPS[DataGroupEnum::EFDg2 as usize];
+        let sod = &DATA_GROUPS[DataGroupEnum::EFSod as usize];
+        let selection = DataGroupSelection::default();
+        assert!(selection.includes(dg1, false));
+        assert!(!selection.includes(dg2, false));
+        assert!(selection.includes(dg2, true));
+
+        let selection = DataGroupSelection {
+            only: Some(vec!["EF.DG1", "EF.DG2"]),
+            skip: vec!["EF.DG1"],
+        };
+        assert!(!selection.includes(dg1, false));
+        assert!(selection.includes(dg2, false));
+        assert!(!selection.includes(sod, true));
+    }
+}
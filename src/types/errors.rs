@@ -10,3 +10,60 @@ impl fmt::Display for ParseError {
         write!(f, "Failed to parse value from given String.")
     }
 }
+
+#[cfg(feature = "vds")]
+#[derive(Debug)]
+pub struct VerificationError {
+    pub additional_text: String,
+}
+
+#[cfg(feature = "vds")]
+impl Error for VerificationError {}
+
+#[cfg(feature = "vds")]
+impl fmt::Display for VerificationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Verification failed: {}", self.additional_text)
+    }
+}
+
+#[derive(Debug)]
+pub struct CardLostError {}
+
+impl Error for CardLostError {}
+
+impl fmt::Display for CardLostError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Lost the connection to the card.")
+    }
+}
+
+#[derive(Debug)]
+pub struct ReadError {
+    pub additional_text: String,
+}
+
+impl Error for ReadError {}
+
+impl fmt::Display for ReadError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Couldn't read the document: {}", self.additional_text)
+    }
+}
+
+#[derive(Debug)]
+pub struct SecureMessagingError {
+    pub additional_text: String,
+}
+
+impl Error for SecureMessagingError {}
+
+impl fmt::Display for SecureMessagingError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Got a broken secure messaging response: {}",
+            self.additional_text
+        )
+    }
+}
@@ -1,9 +1,19 @@
+pub mod consistency;
+#[cfg(feature = "vds")]
+pub mod country_codes;
 pub mod data_groups;
 pub mod errors;
 pub mod mrz;
 pub mod parsed_data_groups;
+#[cfg(feature = "vds")]
+pub mod vds;
 
+pub use self::consistency::*;
+#[cfg(feature = "vds")]
+pub use self::country_codes::*;
 pub use self::data_groups::*;
 pub use self::errors::*;
 pub use self::mrz::*;
 pub use self::parsed_data_groups::*;
+#[cfg(feature = "vds")]
+pub use self::vds::*;
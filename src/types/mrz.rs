@@ -21,28 +21,205 @@ fn validate_mrz_field_check_digit(
     return check_digit_valid;
 }
 
+/// Handles truncated document numbers for TD1 and TD2 MRZs.
+///
+/// Returns (document_number, document_number_check_digit, optional_data_elements)
+fn parse_truncated_document_number(
+    document_number: String,
+    document_number_check_digit: char,
+    optional_data_elements: String,
+) -> Option<(String, char, String)> {
+    // ICAO 9303 p5, Edition 8, 4.2.2.3, Note j says:
+    // "The number of characters in the VIZ may be variable; however, if the document number has more than 9
+    // characters, the 9 principal characters shall be shown in the MRZ in character positions 6 to 14. They shall be
+    // followed by a filler character instead of a check digit to indicate a truncated number. The remaining characters
+    // of the document number shall be shown at the beginning of the field reserved for optional data elements
+    // (character positions 16 to 30 of the upper machine readable line) followed by a check digit and a filler character."
+    // ICAO 9303 p6 has the same rule for TD2, just with different character positions.
+    if document_number_check_digit != '<' {
+        return Some((
+            document_number,
+            document_number_check_digit,
+            optional_data_elements,
+        ));
+    }
+    let mut document_number = document_number;
+    // Find the < separating the rest of document number from optional data elements
+    let end_of_doc_number = optional_data_elements
+        .find('<')
+        .unwrap_or(optional_data_elements.len());
+    if end_of_doc_number == 0 {
+        return None;
+    }
+    // Add the rest of the document number into the document number field and set new check digit
+    document_number.push_str(&optional_data_elements[..end_of_doc_number - 1]);
+    let document_number_check_digit = optional_data_elements.chars().nth(end_of_doc_number - 1)?;
+    // Cut off rest of the document number from optional data elements.
+    // Ensure we don't go over the size. Normally this shouldn't happen if the document number
+    // follows the standard (the filler character is present), but this implementation assumes
+    // that some implementations may max out the size of optional elements.
+    let optional_data_elements = optional_data_elements
+        [min(end_of_doc_number + 1, optional_data_elements.len())..]
+        .to_string();
+    return Some((
+        document_number,
+        document_number_check_digit,
+        optional_data_elements,
+    ));
+}
+
 #[derive(Debug)]
 pub enum MRZ {
     TD1(TD1Mrz),
-    // TD2(TD2Mrz),
+    TD2(TD2Mrz),
     TD3(TD3Mrz),
+    MRVA(MRVMrz),
+    MRVB(MRVMrz),
 }
 
 impl MRZ {
     pub fn deserialize(input: &String) -> Option<MRZ> {
-        match input.len() {
-            90 => Some(MRZ::TD1(TD1Mrz::deserialize(input)?)),
-            88 => Some(MRZ::TD3(TD3Mrz::deserialize(input)?)),
+        // MRZs are ASCII only, and are sliced by byte below.
+        if !input.is_ascii() {
+            return None;
+        }
+        // MRVs share their sizes with TD2 and TD3, but their document code always starts with V.
+        // ICAO 9303 part 5 and 6 both disallow V as the first character for TD1 and TD2.
+        let is_visa = input.starts_with('V');
+        match (input.len(), is_visa) {
+            (90, _) => Some(MRZ::TD1(TD1Mrz::deserialize(input)?)),
+            (72, false) => Some(MRZ::TD2(TD2Mrz::deserialize(input)?)),
+            (88, false) => Some(MRZ::TD3(TD3Mrz::deserialize(input)?)),
+            (88, true) => Some(MRZ::MRVA(MRVMrz::deserialize(input)?)),
+            (72, true) => Some(MRZ::MRVB(MRVMrz::deserialize(input)?)),
             _ => None,
         }
     }
 
+    pub fn document_number(&self) -> &String {
+        match self {
+            Self::TD1(mrzobj) => &mrzobj.document_number,
+            Self::TD2(mrzobj) => &mrzobj.document_number,
+            Self::TD3(mrzobj) => &mrzobj.document_number,
+            Self::MRVA(mrzobj) | Self::MRVB(mrzobj) => &mrzobj.document_number,
+        }
+    }
+
+    pub fn date_of_birth(&self) -> &String {
+        match self {
+            Self::TD1(mrzobj) => &mrzobj.date_of_birth,
+            Self::TD2(mrzobj) => &mrzobj.date_of_birth,
+            Self::TD3(mrzobj) => &mrzobj.date_of_birth,
+            Self::MRVA(mrzobj) | Self::MRVB(mrzobj) => &mrzobj.date_of_birth,
+        }
+    }
+
+    pub fn date_of_expiry(&self) -> &String {
+        match self {
+            Self::TD1(mrzobj) => &mrzobj.date_of_expiry,
+            Self::TD2(mrzobj) => &mrzobj.date_of_expiry,
+            Self::TD3(mrzobj) => &mrzobj.date_of_expiry,
+            Self::MRVA(mrzobj) | Self::MRVB(mrzobj) => &mrzobj.date_of_expiry,
+        }
+    }
+
+    pub fn issuing_state(&self) -> &String {
+        match self {
+            Self::TD1(mrzobj) => &mrzobj.issuing_state,
+            Self::TD2(mrzobj) => &mrzobj.issuing_state,
+            Self::TD3(mrzobj) => &mrzobj.issuing_state,
+            Self::MRVA(mrzobj) | Self::MRVB(mrzobj) => &mrzobj.issuing_state,
+        }
+    }
+
+    pub fn name_of_holder(&self) -> &String {
+        match self {
+            Self::TD1(mrzobj) => &mrzobj.name_of_holder,
+            Self::TD2(mrzobj) => &mrzobj.name_of_holder,
+            Self::TD3(mrzobj) => &mrzobj.name_of_holder,
+            Self::MRVA(mrzobj) | Self::MRVB(mrzobj) => &mrzobj.name_of_holder,
+        }
+    }
+
+    /// Returns the name of the MRZ format, like "TD3".
+    pub fn format_name(&self) -> &'static str {
+        match self {
+            Self::TD1(_) => "TD1",
+            Self::TD2(_) => "TD2",
+            Self::TD3(_) => "TD3",
+            Self::MRVA(_) => "MRV-A",
+            Self::MRVB(_) => "MRV-B",
+        }
+    }
+
+    /// Returns (field name, value) for each data field of the MRZ.
+    ///
+    /// Check digits are left out, as they follow from the fields they cover.
+    pub fn fields(&self) -> Vec<(&'static str, String)> {
+        let (document_code, issuing_state, nationality, sex, optional_data) = match self {
+            Self::TD1(mrzobj) => (
+                &mrzobj.document_code,
+                &mrzobj.issuing_state,
+                &mrzobj.nationality,
+                mrzobj.sex,
+                vec![
+                    (
+                        "Optional data (line 1)",
+                        mrzobj.optional_data_elements_line_1.to_string(),
+                    ),
+                    (
+                        "Optional data (line 2)",
+                        mrzobj.optional_data_elements_line_2.to_string(),
+                    ),
+                ],
+            ),
+            Self::TD2(mrzobj) => (
+                &mrzobj.document_code,
+                &mrzobj.issuing_state,
+                &mrzobj.nationality,
+                mrzobj.sex,
+                vec![("Optional data", mrzobj.optional_data_elements.to_string())],
+            ),
+            Self::TD3(mrzobj) => (
+                &mrzobj.document_code,
+                &mrzobj.issuing_state,
+                &mrzobj.nationality,
+                mrzobj.sex,
+                vec![(
+                    "Personal number",
+                    mrzobj.personal_number_or_optional_data_elements.to_string(),
+                )],
+            ),
+            Self::MRVA(mrzobj) | Self::MRVB(mrzobj) => (
+                &mrzobj.document_code,
+                &mrzobj.issuing_state,
+                &mrzobj.nationality,
+                mrzobj.sex,
+                vec![("Optional data", mrzobj.optional_data_elements.to_string())],
+            ),
+        };
+        let mut fields = vec![
+            ("Document code", document_code.to_string()),
+            ("Issuing state", issuing_state.to_string()),
+            ("Document number", self.document_number().to_string()),
+            ("Name of holder", self.name_of_holder().to_string()),
+            ("Nationality", nationality.to_string()),
+            ("Date of birth", self.date_of_birth().to_string()),
+            ("Sex", sex.to_string()),
+            ("Date of expiry", self.date_of_expiry().to_string()),
+        ];
+        fields.extend(optional_data);
+        return fields;
+    }
+
     // allowing dead code here because I think this is a useful API as a library
     #[allow(dead_code)]
     pub fn validate_check_digits(&self, verbose: bool) -> Vec<bool> {
         match self {
             Self::TD1(mrzobj) => mrzobj.validate_check_digits(verbose),
+            Self::TD2(mrzobj) => mrzobj.validate_check_digits(verbose),
             Self::TD3(mrzobj) => mrzobj.validate_check_digits(verbose),
+            Self::MRVA(mrzobj) | Self::MRVB(mrzobj) => mrzobj.validate_check_digits(verbose),
         }
     }
 }
@@ -194,35 +371,12 @@ impl TD1Mrz {
         if input.len() != 90 {
             return None;
         }
-        // ICAO 9303 p5, Edition 8, 4.2.2.3, Note j says:
-        // "The number of characters in the VIZ may be variable; however, if the document number has more than 9
-        // characters, the 9 principal characters shall be shown in the MRZ in character positions 6 to 14. They shall be
-        // followed by a filler character instead of a check digit to indicate a truncated number. The remaining characters
-        // of the document number shall be shown at the beginning of the field reserved for optional data elements
-        // (character positions 16 to 30 of the upper machine readable line) followed by a check digit and a filler character."
-        let mut document_number = dg_helpers::remove_mrz_padding(&input[5..14].to_string());
-        let mut document_number_check_digit = input.chars().nth(14)?;
-        let mut optional_data_elements_line_1 =
-            dg_helpers::remove_mrz_padding(&input[15..30].to_string());
-        // Check if this is truncated document number
-        if document_number_check_digit == '<' {
-            // Find the < separating the rest of document number from optional data elements
-            let end_of_doc_number = optional_data_elements_line_1
-                .find('<')
-                .unwrap_or(optional_data_elements_line_1.len());
-            // Add the rest of the document number into the document number field and set new check digit
-            document_number.push_str(&optional_data_elements_line_1[..end_of_doc_number - 1]);
-            document_number_check_digit = optional_data_elements_line_1
-                .chars()
-                .nth(end_of_doc_number - 1)?;
-            // Cut off rest of the document number from optional data elements.
-            // Ensure we don't go over the size. Normally this shouldn't happen if the document number
-            // follows the standard (the filler character is present), but this implementation assumes
-            // that some implementations may max out the size of optional elements.
-            optional_data_elements_line_1 = optional_data_elements_line_1
-                [min(end_of_doc_number + 1, optional_data_elements_line_1.len())..]
-                .to_string();
-        }
+        let (document_number, document_number_check_digit, optional_data_elements_line_1) =
+            parse_truncated_document_number(
+                dg_helpers::remove_mrz_padding(&input[5..14].to_string()),
+                input.chars().nth(14)?,
+                dg_helpers::remove_mrz_padding(&input[15..30].to_string()),
+            )?;
         return Some(TD1Mrz {
             raw_mrz: input.to_string(),
             // Line 1
@@ -265,6 +419,128 @@ impl TD1Mrz {
     }
 }
 
+#[derive(Debug, Clone, PartialEq)]
+pub struct TD2Mrz {
+    // ICAO 9303 part 6, edition 8, 4.2.2
+    /// 72 characters of MRZ (physically shown as 2 lines)
+    pub raw_mrz: String,
+    // Line 1
+    /// 2 characters. The first character shall be A, C or I.
+    /// The second character shall be as specified in ICAO 9303 part 6,
+    /// edition 8, 4.2.2.3 Note j.
+    pub document_code: String,
+    /// The three-letter code specified in Doc 9303-3 shall be used.
+    pub issuing_state: String,
+    /// 31 characters.
+    pub name_of_holder: String,
+    // Line 2
+    /// 9 characters
+    pub document_number: String,
+    /// 1 character
+    pub document_number_check_digit: char,
+    /// The three-letter code specified in Doc 9303-3 shall be used.
+    pub nationality: String,
+    /// 6 characters, YYMMDD
+    pub date_of_birth: String,
+    /// 1 character
+    pub date_of_birth_check_digit: char,
+    /// F = female; M = male; < = unspecified.
+    pub sex: char,
+    /// 6 characters, YYMMDD
+    pub date_of_expiry: String,
+    /// 1 character
+    pub date_of_expiry_check_digit: char,
+    /// up to 7 characters
+    pub optional_data_elements: String,
+    /// 1 character
+    pub composite_check_digit: char,
+}
+
+impl MRZChecksum for TD2Mrz {
+    fn get_checksum_variables(
+        &self,
+    ) -> (
+        &String,
+        &char,
+        &String,
+        &char,
+        &String,
+        &char,
+        String,
+        &char,
+    ) {
+        // ICAO 9303 p6, edition 8, 4.2.2.2 says:
+        // "Composite check digit for characters of machine readable data of the lower line
+        // in positions 1 to 10, 14 to 20 and 22 to 35, including values for letters that are
+        // a part of the number fields and their check digits."
+        let composite_base = vec![
+            &self.raw_mrz[36..36 + 10],
+            &self.raw_mrz[36 + 13..36 + 20],
+            &self.raw_mrz[36 + 21..36 + 35],
+        ]
+        .concat();
+
+        return (
+            &self.document_number,
+            &self.document_number_check_digit,
+            &self.date_of_birth,
+            &self.date_of_birth_check_digit,
+            &self.date_of_expiry,
+            &self.date_of_expiry_check_digit,
+            composite_base,
+            &self.composite_check_digit,
+        );
+    }
+}
+
+impl TD2Mrz {
+    pub fn deserialize(input: &String) -> Option<TD2Mrz> {
+        if input.len() != 72 {
+            return None;
+        }
+        let (document_number, document_number_check_digit, optional_data_elements) =
+            parse_truncated_document_number(
+                dg_helpers::remove_mrz_padding(&input[36..45].to_string()),
+                input.chars().nth(45)?,
+                dg_helpers::remove_mrz_padding(&input[64..71].to_string()),
+            )?;
+        return Some(TD2Mrz {
+            raw_mrz: input.to_string(),
+            // Line 1
+            document_code: input[0..2].to_string(),
+            issuing_state: dg_helpers::remove_mrz_padding(&input[2..5].to_string()),
+            name_of_holder: dg_helpers::remove_mrz_padding(&input[5..36].to_string()),
+            // Line 2
+            document_number: document_number,
+            document_number_check_digit: document_number_check_digit,
+            nationality: dg_helpers::remove_mrz_padding(&input[46..49].to_string()),
+            date_of_birth: input[49..55].to_string(),
+            date_of_birth_check_digit: input.chars().nth(55)?,
+            sex: input.chars().nth(56)?,
+            date_of_expiry: input[57..63].to_string(),
+            date_of_expiry_check_digit: input.chars().nth(63)?,
+            optional_data_elements: optional_data_elements,
+            composite_check_digit: input.chars().nth(71)?,
+        });
+    }
+
+    /// Returns (document_number_valid, date_of_birth_valid, date_of_expiry_valid,
+    /// composite_valid)
+    ///
+    /// verbose argument makes invalid check digits to log as warn.
+    pub fn validate_check_digits(&self, verbose: bool) -> Vec<bool> {
+        let (document_number_valid, date_of_birth_valid, date_of_expiry_valid, composite_valid) =
+            self.calculate_common_checksums(verbose);
+
+        return vec![
+            document_number_valid,
+            date_of_birth_valid,
+            date_of_expiry_valid,
+            composite_valid,
+        ];
+    }
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct TD3Mrz {
     // ICAO 9303 part 4, edition 8, 4.2.2
@@ -397,6 +673,111 @@ impl TD3Mrz {
     }
 }
 
+#[derive(Debug, Clone, PartialEq)]
+pub struct MRVMrz {
+    // ICAO 9303 part 7, edition 8, 4.2.2 (MRV-A) and 5.2.2 (MRV-B)
+    /// 88 (MRV-A) or 72 (MRV-B) characters of MRZ (physically shown as 2 lines)
+    pub raw_mrz: String,
+    // Line 1
+    /// 2 characters. The first character shall be V to designate a machine readable visa.
+    /// The second character shall be at the discretion of the issuing State.
+    pub document_code: String,
+    /// The three-letter code specified in Doc 9303-3 shall be used.
+    pub issuing_state: String,
+    /// 39 (MRV-A) or 31 (MRV-B) characters.
+    pub name_of_holder: String,
+    // Line 2
+    /// 9 characters, the visa number.
+    pub document_number: String,
+    /// 1 character
+    pub document_number_check_digit: char,
+    /// The three-letter code specified in Doc 9303-3 shall be used.
+    pub nationality: String,
+    /// 6 characters, YYMMDD
+    pub date_of_birth: String,
+    /// 1 character
+    pub date_of_birth_check_digit: char,
+    /// F = female; M = male; < = unspecified.
+    pub sex: char,
+    /// 6 characters, YYMMDD. This is the "valid until" date of the visa.
+    pub date_of_expiry: String,
+    /// 1 character
+    pub date_of_expiry_check_digit: char,
+    /// up to 16 (MRV-A) or 8 (MRV-B) characters, not covered by a check digit.
+    pub optional_data_elements: String,
+}
+
+impl MRVMrz {
+    pub fn deserialize(input: &String) -> Option<MRVMrz> {
+        // MRVs have no composite check digit, and the optional data elements
+        // run until the end of the lower line, so only the line length differs.
+        let line_len = match input.len() {
+            88 => 44,
+            72 => 36,
+            _ => return None,
+        };
+        return Some(MRVMrz {
+            raw_mrz: input.to_string(),
+            // Line 1
+            document_code: input[0..2].to_string(),
+            issuing_state: dg_helpers::remove_mrz_padding(&input[2..5].to_string()),
+            name_of_holder: dg_helpers::remove_mrz_padding(&input[5..line_len].to_string()),
+            // Line 2
+            document_number: dg_helpers::remove_mrz_padding(
+                &input[line_len..line_len + 9].to_string(),
+            ),
+            document_number_check_digit: input.chars().nth(line_len + 9)?,
+            nationality: dg_helpers::remove_mrz_padding(
+                &input[line_len + 10..line_len + 13].to_string(),
+            ),
+            date_of_birth: input[line_len + 13..line_len + 19].to_string(),
+            date_of_birth_check_digit: input.chars().nth(line_len + 19)?,
+            sex: input.chars().nth(line_len + 20)?,
+            date_of_expiry: input[line_len + 21..line_len + 27].to_string(),
+            date_of_expiry_check_digit: input.chars().nth(line_len + 27)?,
+            optional_data_elements: dg_helpers::remove_mrz_padding(
+                &input[line_len + 28..].to_string(),
+            ),
+        });
+    }
+
+    /// Returns true for MRV-A (2x44), false for MRV-B (2x36).
+    pub fn is_mrv_a(&self) -> bool {
+        return self.raw_mrz.len() == 88;
+    }
+
+    /// Returns (document_number_valid, date_of_birth_valid, date_of_expiry_valid)
+    ///
+    /// verbose argument makes invalid check digits to log as warn.
+    pub fn validate_check_digits(&self, verbose: bool) -> Vec<bool> {
+        // MRVs don't have a composite check digit, so we can't use MRZChecksum here.
+        let document_number_valid = validate_mrz_field_check_digit(
+            &self.document_number,
+            &self.document_number_check_digit,
+            verbose,
+            Some("Document number".to_string()),
+        );
+        let date_of_birth_valid = validate_mrz_field_check_digit(
+            &self.date_of_birth,
+            &self.date_of_birth_check_digit,
+            verbose,
+            Some("Date of birth".to_string()),
+        );
+        let date_of_expiry_valid = validate_mrz_field_check_digit(
+            &self.date_of_expiry,
+            &self.date_of_expiry_check_digit,
+            verbose,
+            Some("Date of expiry".to_string()),
+        );
+
+        return vec![
+            document_number_valid,
+            date_of_birth_valid,
+            date_of_expiry_valid,
+        ];
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -427,4 +808,54 @@ mod tests {
         assert_eq!(result.document_number_check_digit, '6');
         assert_eq!(result.optional_data_elements_line_1, "");
     }
+
+    #[test]
+    fn td2_mrz_parsing() {
+        let mrz =
+            &"I<UTOERIKSSON<<ANNA<MARIA<<<<<<<<<<<D231458907UTO7408122F1204159<<<<<<<6".to_string();
+        let result = TD2Mrz::deserialize(mrz).unwrap();
+        assert_eq!(result.issuing_state, "UTO");
+        assert_eq!(result.name_of_holder, "ERIKSSON<<ANNA<MARIA");
+        assert_eq!(result.document_number, "D23145890");
+        assert_eq!(result.date_of_birth, "740812");
+        assert_eq!(result.date_of_expiry, "120415");
+        assert_eq!(result.optional_data_elements, "");
+        assert!(result.validate_check_digits(false).iter().all(|&val| val));
+    }
+
+    #[test]
+    fn mrv_a_mrz_parsing() {
+        let mrz = &"V<UTOERIKSSON<<ANNA<MARIA<<<<<<<<<<<<<<<<<<<L8988901C4XXX4009078F96121096ZE184226B<<<<<<".to_string();
+        let result = match MRZ::deserialize(mrz).unwrap() {
+            MRZ::MRVA(mrzobj) => mrzobj,
+            other => panic!("Expected MRV-A, got {:?}", other),
+        };
+        assert_eq!(result.document_number, "L8988901C");
+        assert_eq!(result.nationality, "XXX");
+        assert_eq!(result.date_of_expiry, "961210");
+        assert_eq!(result.optional_data_elements, "6ZE184226B");
+        assert!(result.validate_check_digits(false).iter().all(|&val| val));
+    }
+
+    #[test]
+    fn mrv_b_mrz_parsing() {
+        let mrz =
+            &"V<UTOERIKSSON<<ANNA<MARIA<<<<<<<<<<<L8988901C4XXX4009078F9612109<<<<<<<<".to_string();
+        let result = match MRZ::deserialize(mrz).unwrap() {
+            MRZ::MRVB(mrzobj) => mrzobj,
+            other => panic!("Expected MRV-B, got {:?}", other),
+        };
+        assert_eq!(result.name_of_holder, "ERIKSSON<<ANNA<MARIA");
+        assert_eq!(result.document_number, "L8988901C");
+        assert_eq!(result.optional_data_elements, "");
+        assert!(result.validate_check_digits(false).iter().all(|&val| val));
+    }
+
+    #[test]
+    fn non_ascii_mrz_parsing() {
+        // 89 characters, but 90 bytes with the document number cut in the middle of the É.
+        let mrz = &"I<UTO12345678É7ABCDEFGH<<<<<<<0001029<3001020UTO<<<<<<<<<<<8MUSTERMANN<<ERIKA<<<<<<<<<<<<".to_string();
+        assert_eq!(mrz.len(), 90);
+        assert!(MRZ::deserialize(mrz).is_none());
+    }
 }
@@ -1,5 +1,5 @@
 use crate::types::MRZ;
-use strum::FromRepr;
+use strum::{FromRepr, IntoStaticStr};
 
 #[derive(Debug)]
 pub struct EFCom {
@@ -9,31 +9,252 @@ pub struct EFCom {
     pub data_group_tag_list: Vec<u8>,
 }
 
+#[derive(Debug)]
+pub struct EFAtrInfo {
+    // ISO/IEC 7816-4, 12.2.2 and 12.7
+    pub card_service_data: Option<u8>,
+    /// Software function tables, the first one being the selection methods
+    pub card_capabilities: Option<Vec<u8>>,
+    pub command_chaining: bool,
+    /// Whether extended Lc and Le fields are supported
+    pub extended_length: bool,
+    /// Max bytes in a command APDU, from the extended length information
+    pub max_command_len: Option<usize>,
+    /// Max bytes in a response APDU, from the extended length information
+    pub max_response_len: Option<usize>,
+}
+
+#[derive(Debug)]
+pub struct Application {
+    // ISO/IEC 7816-4, 8.2.1.1
+    pub aid: Vec<u8>,
+    pub label: Option<String>,
+}
+
+#[derive(Debug)]
+pub struct EFDir {
+    // ICAO 9303 part 10, edition 8, 3.10
+    pub applications: Vec<Application>,
+}
+
 #[derive(Debug)]
 pub struct EFDG1 {
     // ICAO 9303 part 10, edition 8, 4.7.1
     pub mrz: MRZ,
 }
 
-#[derive(Debug, FromRepr, PartialEq, Clone, Copy)]
+#[derive(Debug, PartialEq, Clone, Copy)]
 pub enum BiometricImageFormat {
-    // ISO/IEC 19794:5-2005, 5.7.2
-    Jpeg = 0x00,
-    Jpeg2000 = 0x01,
-    Reserved = 0x02,
+    Jpeg,
+    Jpeg2000,
+    Png,
+    Pgm,
+    Ppm,
+    Wsq,
+    JpegLs,
+    /// Uncompressed pixels, without any header
+    Raw,
+    Reserved,
 }
 
 impl BiometricImageFormat {
+    /// Converts an ISO/IEC 19794-5:2005 image data type (5.7.2)
+    pub fn from_iso19794_5(code: u8) -> BiometricImageFormat {
+        match code {
+            0x00 => BiometricImageFormat::Jpeg,
+            0x01 => BiometricImageFormat::Jpeg2000,
+            _ => BiometricImageFormat::Reserved,
+        }
+    }
+
+    /// Converts an ISO/IEC 19794-4:2005 image compression algorithm (8.3.15)
+    pub fn from_iso19794_4(code: u8) -> BiometricImageFormat {
+        match code {
+            // Uncompressed, with and without bit packing
+            0x00 | 0x01 => BiometricImageFormat::Raw,
+            0x02 => BiometricImageFormat::Wsq,
+            0x03 => BiometricImageFormat::Jpeg,
+            0x04 => BiometricImageFormat::Jpeg2000,
+            0x05 => BiometricImageFormat::Png,
+            _ => BiometricImageFormat::Reserved,
+        }
+    }
+
+    /// Converts an ISO/IEC 19794-6:2005 image format (6.5.1.9), both monochrome and RGB
+    pub fn from_iso19794_6(code: u16) -> BiometricImageFormat {
+        match code {
+            0x0002 | 0x0004 => BiometricImageFormat::Raw,
+            0x0006 | 0x0008 => BiometricImageFormat::Jpeg,
+            0x000A | 0x000C => BiometricImageFormat::JpegLs,
+            0x000E | 0x0010 => BiometricImageFormat::Jpeg2000,
+            _ => BiometricImageFormat::Reserved,
+        }
+    }
+
+    /// Converts an ISO/IEC 39794-5:2019 image data format code
+    pub fn from_iso39794_5(code: i64) -> BiometricImageFormat {
+        match code {
+            2 => BiometricImageFormat::Jpeg,
+            // JPEG 2000 lossy and lossless
+            3 | 4 => BiometricImageFormat::Jpeg2000,
+            5 => BiometricImageFormat::Png,
+            6 => BiometricImageFormat::Pgm,
+            7 => BiometricImageFormat::Ppm,
+            _ => BiometricImageFormat::Reserved,
+        }
+    }
+
+    /// Detects the format of an image from its magic bytes
+    pub fn from_magic(data: &[u8]) -> Option<BiometricImageFormat> {
+        if data.starts_with(&[0xFF, 0xD8, 0xFF]) {
+            return Some(BiometricImageFormat::Jpeg);
+        }
+        // JP2 signature box, or a bare JPEG 2000 codestream
+        if data.starts_with(&[0x00, 0x00, 0x00, 0x0C, 0x6A, 0x50, 0x20, 0x20])
+            || data.starts_with(&[0xFF, 0x4F, 0xFF, 0x51])
+        {
+            return Some(BiometricImageFormat::Jpeg2000);
+        }
+        if data.starts_with(&[0x89, 0x50, 0x4E, 0x47]) {
+            return Some(BiometricImageFormat::Png);
+        }
+        return None;
+    }
+
     pub fn get_extension(&self) -> String {
         match &self {
             BiometricImageFormat::Jpeg => "jpeg",
             BiometricImageFormat::Jpeg2000 => "jp2",
+            BiometricImageFormat::Png => "png",
+            BiometricImageFormat::Pgm => "pgm",
+            BiometricImageFormat::Ppm => "ppm",
+            BiometricImageFormat::Wsq => "wsq",
+            BiometricImageFormat::JpegLs => "jls",
+            BiometricImageFormat::Raw => "raw",
             BiometricImageFormat::Reserved => "image_bin",
         }
         .to_string()
     }
 }
 
+#[derive(Debug, Clone, PartialEq)]
+pub struct FacePoseAngle {
+    /// Degrees, positive is the face turned to its left
+    pub yaw: Option<i16>,
+    /// Degrees, positive is the face looking down
+    pub pitch: Option<i16>,
+    /// Degrees, positive is the face tilted to its left shoulder
+    pub roll: Option<i16>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FaceFeaturePoint {
+    // ISO/IEC 19794-5:2005, 5.6
+    /// 1 = 2D MPEG4 feature point
+    pub point_type: u8,
+    /// MPEG4 feature point, as in "major.minor"
+    pub major_code: u8,
+    pub minor_code: u8,
+    pub x: u16,
+    pub y: u16,
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct FaceDetails {
+    // ISO/IEC 19794-5:2005, 5.5 to 5.7 / ISO/IEC 39794-5:2019, 7.2
+    pub gender: Option<String>,
+    pub eye_colour: Option<String>,
+    pub hair_colour: Option<String>,
+    pub properties: Option<Vec<String>>,
+    pub expression: Option<String>,
+    pub pose_angle: Option<FacePoseAngle>,
+    pub feature_points: Vec<FaceFeaturePoint>,
+    pub face_image_type: Option<String>,
+    pub colour_space: Option<String>,
+    pub source_type: Option<String>,
+    pub image_width: Option<u32>,
+    pub image_height: Option<u32>,
+}
+
+#[derive(Debug, FromRepr, IntoStaticStr, PartialEq, Clone, Copy)]
+#[strum(serialize_all = "snake_case")]
+pub enum FingerPosition {
+    // ISO/IEC 19794-4:2005, 8.3.1, Table 2
+    Unknown = 0,
+    RightThumb = 1,
+    RightIndexFinger = 2,
+    RightMiddleFinger = 3,
+    RightRingFinger = 4,
+    RightLittleFinger = 5,
+    LeftThumb = 6,
+    LeftIndexFinger = 7,
+    LeftMiddleFinger = 8,
+    LeftRingFinger = 9,
+    LeftLittleFinger = 10,
+    PlainRightFourFingers = 13,
+    PlainLeftFourFingers = 14,
+    PlainThumbs = 15,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FingerDetails {
+    // ISO/IEC 19794-4:2005, 8.2 and 8.3
+    pub finger_position: FingerPosition,
+    pub view_count: u8,
+    pub view_number: u8,
+    /// 0-100, 254 is not available, 255 is unknown
+    pub quality: u8,
+    pub impression_type: String,
+    pub compression_algorithm: String,
+    /// Pixels per inch or pixels per centimeter
+    pub scale_units: String,
+    pub horizontal_image_resolution: u16,
+    pub vertical_image_resolution: u16,
+    pub pixel_depth: u8,
+    pub image_width: u16,
+    pub image_height: u16,
+}
+
+#[derive(Debug, FromRepr, IntoStaticStr, PartialEq, Clone, Copy)]
+#[strum(serialize_all = "snake_case")]
+pub enum EyeLabel {
+    // ISO/IEC 19794-6:2005, 6.5.2.1
+    Undefined = 0,
+    RightEye = 1,
+    LeftEye = 2,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct IrisDetails {
+    // ISO/IEC 19794-6:2005, 6.5
+    pub eye_label: EyeLabel,
+    /// Amount of images of this eye
+    pub image_count: u16,
+    pub image_sequence_number: u16,
+    /// 0-100, 254 is not available, 255 is unknown
+    pub quality: u8,
+    /// Degrees, None if undefined
+    pub rotation_angle: Option<f32>,
+    /// Scan type, like progressive or interlaced
+    pub image_type: String,
+    /// Monochrome or RGB, and the compression
+    pub image_format: String,
+    pub capture_device_id: u16,
+    pub device_unique_identifier: Option<String>,
+    /// Pixels, None if undefined
+    pub iris_diameter: Option<u16>,
+    pub intensity_depth: u8,
+    pub image_width: u16,
+    pub image_height: u16,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum BiometricDetails {
+    Face(FaceDetails),
+    Finger(FingerDetails),
+    Iris(IrisDetails),
+}
+
 #[derive(Debug)]
 pub struct Biometric {
     // ICAO 9303 part 10, edition 8, 4.7.2.1
@@ -48,6 +269,8 @@ pub struct Biometric {
     pub format_type: Vec<u8>,
     pub data: Vec<u8>,
     pub image_format: BiometricImageFormat,
+    /// Metadata from the biometric record, if the record format is supported
+    pub details: Option<BiometricDetails>,
 }
 
 #[derive(Debug)]
@@ -109,14 +332,61 @@ pub struct EFDG12 {
     pub personalization_device_serial_number: Option<String>,
 }
 
+#[derive(Debug)]
+pub struct EFDG13 {
+    // ICAO 9303 part 10, edition 8, 4.7.13
+    /// Contents of the file, its layout is at the discretion of the issuing state
+    pub data: Vec<u8>,
+    /// Name of the known layout the fields were decoded with
+    pub layout: Option<&'static str>,
+    /// (field name, value) for each decoded field
+    pub fields: Vec<(&'static str, String)>,
+}
+
+#[derive(Debug)]
+pub struct PersonToNotify {
+    // ICAO 9303 part 10, edition 8, 4.7.16
+    /// YYYYMMDD
+    pub date_recorded: Option<String>,
+    pub name: Option<String>,
+    pub telephone: Option<String>,
+    pub address: Option<String>,
+}
+
+#[derive(Debug)]
+pub struct EFDG16 {
+    // ICAO 9303 part 10, edition 8, 4.7.16
+    pub persons_to_notify: Vec<PersonToNotify>,
+}
+
+#[derive(Debug)]
+pub struct DataGroupHash {
+    // ICAO 9303 part 10, edition 8, 4.6.2.2
+    pub dg_num: u8,
+    pub hash: Vec<u8>,
+}
+
+#[derive(Debug)]
+pub struct EFSod {
+    // ICAO 9303 part 10, edition 8, 4.6.2
+    /// OID of the algorithm the data groups were hashed with
+    pub hash_algorithm: String,
+    pub data_group_hashes: Vec<DataGroupHash>,
+}
+
 #[allow(dead_code)]
 #[derive(Debug)]
 pub enum ParsedDataGroup {
     EFCom(EFCom),
+    EFAtrInfo(EFAtrInfo),
+    EFDir(EFDir),
     EFDG1(EFDG1),
     EFDG2_3_4(EFDG2_3_4),
     EFDG5(EFDG5),
     EFDG7(EFDG7),
     EFDG11(EFDG11),
     EFDG12(EFDG12),
+    EFDG13(EFDG13),
+    EFDG16(EFDG16),
+    EFSod(EFSod),
 }
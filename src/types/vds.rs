@@ -0,0 +1,387 @@
+use crate::{dg_parsers::helpers as dg_helpers, helpers, trust_store, types};
+use simplelog::debug;
+
+// ICAO 9303 part 13, edition 8, 2.2
+const VDS_MAGIC_CONSTANT: u8 = 0xDC;
+const VDS_SIGNATURE_MARKER: u8 = 0xFF;
+// Version byte is the version number minus one.
+const VDS_VERSION_3: u8 = 0x02;
+const VDS_VERSION_4: u8 = 0x03;
+
+/// Decodes C40 encoded data as used by VDS (ICAO 9303 part 13, edition 8, 2.6)
+///
+/// Spaces are returned as the MRZ filler character (<), as ICAO does that mapping as well.
+pub fn c40_decode(data: &[u8]) -> String {
+    let mut result = String::new();
+    for chunk in data.chunks(2) {
+        // A lone trailing character is encoded as 0xFE followed by ASCII value + 1.
+        if chunk[0] == 0xFE {
+            if chunk.len() == 2 {
+                result.push(char::from(chunk[1].saturating_sub(1)));
+            }
+            continue;
+        }
+        if chunk.len() != 2 {
+            break;
+        }
+        let value = u16::from_be_bytes([chunk[0], chunk[1]]).saturating_sub(1);
+        for c40_value in [value / 1600, (value / 40) % 40, value % 40] {
+            match c40_value {
+                // Shift characters are only used for padding here.
+                0..=2 => {}
+                3 => result.push('<'),
+                4..=13 => result.push(char::from(b'0' + (c40_value - 4) as u8)),
+                14..=39 => result.push(char::from(b'A' + (c40_value - 14) as u8)),
+                _ => {}
+            }
+        }
+    }
+    return result;
+}
+
+/// Returns the amount of bytes needed to C40 encode the given amount of characters.
+fn c40_encoded_len(char_count: usize) -> usize {
+    return (char_count / 3) * 2 + if char_count % 3 == 0 { 0 } else { 2 };
+}
+
+/// Decodes a VDS date (3 bytes, MMDDYYYY as an integer)
+///
+/// Returns the date as YYYYMMDD, to match the DG date format.
+fn decode_vds_date(data: &[u8]) -> Option<String> {
+    if data.len() != 3 {
+        return None;
+    }
+    let date_number = u32::from_be_bytes([0, data[0], data[1], data[2]]);
+    let date_text = format!("{:08}", date_number);
+    return Some(format!(
+        "{}{}{}",
+        &date_text[4..8],
+        &date_text[0..2],
+        &date_text[2..4]
+    ));
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum VdsDocumentType {
+    // ICAO 9303 part 13, edition 8, 4.1 and 4.2
+    /// Document feature definition reference 0x5D, document type category 0x01
+    Visa,
+    /// Document feature definition reference 0x5E, document type category 0x03
+    EmergencyTravelDocument,
+    /// (document feature definition reference, document type category)
+    Unknown(u8, u8),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct VdsHeader {
+    // ICAO 9303 part 13, edition 8, 2.2
+    /// 3 or 4
+    pub version: u8,
+    /// The three-letter code specified in Doc 9303-3 shall be used.
+    pub issuing_country: String,
+    /// 4 characters, two-letter country code followed by two alphanumeric characters.
+    pub signer_identifier: String,
+    /// Hex string of the serial number of the signer certificate
+    pub certificate_reference: String,
+    /// YYYYMMDD
+    pub document_issue_date: Option<String>,
+    /// YYYYMMDD
+    pub signature_creation_date: Option<String>,
+    pub document_feature_definition_reference: u8,
+    pub document_type_category: u8,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct VdsFeature {
+    pub tag: u8,
+    pub value: Vec<u8>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct VisibleDigitalSeal {
+    pub header: VdsHeader,
+    /// Features in the message zone, in the order they're stored in.
+    pub features: Vec<VdsFeature>,
+    /// Raw ECDSA signature (r || s), if the seal is signed.
+    pub signature: Option<Vec<u8>>,
+    /// Header and message zone, which is what the signature covers.
+    pub signed_data: Vec<u8>,
+}
+
+impl VisibleDigitalSeal {
+    /// Decodes the raw bytes of a VDS barcode.
+    pub fn deserialize(data: &[u8]) -> Option<VisibleDigitalSeal> {
+        if data.len() < 2 || data[0] != VDS_MAGIC_CONSTANT {
+            debug!("VDS magic constant not found: {:02x?}", data.first());
+            return None;
+        }
+        let version = data[1];
+        let issuing_country = c40_decode(data.get(2..4)?);
+
+        // Signer identifier and certificate reference
+        let (signer_identifier, certificate_reference, mut offset) = match version {
+            VDS_VERSION_3 => {
+                // 4 character signer identifier and 5 character certificate reference, in 6 bytes
+                let signer_and_reference = c40_decode(data.get(4..10)?);
+                if signer_and_reference.len() != 9 {
+                    return None;
+                }
+                (
+                    signer_and_reference[0..4].to_string(),
+                    signer_and_reference[4..].to_string(),
+                    10,
+                )
+            }
+            VDS_VERSION_4 => {
+                // 4 character signer identifier and 2 character hex length of certificate reference
+                let signer_and_len = c40_decode(data.get(4..8)?);
+                if signer_and_len.len() != 6 {
+                    return None;
+                }
+                let reference_len = usize::from_str_radix(&signer_and_len[4..6], 16).ok()?;
+                let reference_end = 8 + c40_encoded_len(reference_len);
+                (
+                    signer_and_len[0..4].to_string(),
+                    c40_decode(data.get(8..reference_end)?),
+                    reference_end,
+                )
+            }
+            _ => {
+                debug!("Unsupported VDS version byte: {:02x}", version);
+                return None;
+            }
+        };
+
+        let header = VdsHeader {
+            version: version + 1,
+            issuing_country: dg_helpers::remove_mrz_padding(&issuing_country),
+            signer_identifier: signer_identifier,
+            certificate_reference: certificate_reference,
+            document_issue_date: decode_vds_date(data.get(offset..offset + 3)?),
+            signature_creation_date: decode_vds_date(data.get(offset + 3..offset + 6)?),
+            document_feature_definition_reference: *data.get(offset + 6)?,
+            document_type_category: *data.get(offset + 7)?,
+        };
+        offset += 8;
+        debug!("VDS header: {:?}", header);
+
+        // Message zone, which is a list of tag, DER length, value until the signature marker.
+        let mut features: Vec<VdsFeature> = vec![];
+        while offset < data.len() && data[offset] != VDS_SIGNATURE_MARKER {
+            let tag = data[offset];
+            let (len_field_size, value_len) = helpers::asn1_parse_len(data.get(offset + 1..)?)?;
+            let value_start = offset + 1 + len_field_size as usize;
+            let value_end = value_start + value_len as usize;
+            features.push(VdsFeature {
+                tag: tag,
+                value: data.get(value_start..value_end)?.to_vec(),
+            });
+            offset = value_end;
+        }
+        let signed_data = data[..offset].to_vec();
+
+        // Signature zone, which is the marker, DER length and the signature.
+        let mut signature: Option<Vec<u8>> = None;
+        if offset < data.len() {
+            let (len_field_size, value_len) = helpers::asn1_parse_len(data.get(offset + 1..)?)?;
+            let value_start = offset + 1 + len_field_size as usize;
+            signature = Some(
+                data.get(value_start..value_start + value_len as usize)?
+                    .to_vec(),
+            );
+        }
+
+        return Some(VisibleDigitalSeal {
+            header: header,
+            features: features,
+            signature: signature,
+            signed_data: signed_data,
+        });
+    }
+
+    pub fn document_type(&self) -> VdsDocumentType {
+        match (
+            self.header.document_feature_definition_reference,
+            self.header.document_type_category,
+        ) {
+            (0x5D, 0x01) => VdsDocumentType::Visa,
+            (0x5E, 0x03) => VdsDocumentType::EmergencyTravelDocument,
+            (reference, category) => VdsDocumentType::Unknown(reference, category),
+        }
+    }
+
+    pub fn get_feature(&self, tag: u8) -> Option<&Vec<u8>> {
+        return self
+            .features
+            .iter()
+            .find(|feature| feature.tag == tag)
+            .map(|feature| &feature.value);
+    }
+
+    /// Returns the MRZ stored in the seal, as an MRZ object if possible.
+    ///
+    /// VDS leave out the optional data elements of the lower MRZ line, so those are
+    /// filled with filler characters before parsing.
+    pub fn mrz(&self) -> Option<types::MRZ> {
+        let (mrz_text, line_len) = match self.document_type() {
+            // MRV-A, 72 characters in 48 bytes
+            VdsDocumentType::Visa if self.get_feature(0x01).is_some() => {
+                (c40_decode(self.get_feature(0x01)?), 44)
+            }
+            // MRV-B, 64 characters in 44 bytes
+            VdsDocumentType::Visa => (c40_decode(self.get_feature(0x02)?), 36),
+            _ => return None,
+        };
+        // line 1 + the first 28 characters of line 2
+        if mrz_text.len() != line_len + 28 {
+            return None;
+        }
+        let padded_mrz = format!("{:<<width$}", mrz_text, width = line_len * 2);
+        return types::MRZ::deserialize(&padded_mrz);
+    }
+
+    /// Returns the raw MRZ text stored in the seal, for document types we can't parse.
+    pub fn raw_mrz(&self) -> Option<String> {
+        match self.document_type() {
+            VdsDocumentType::Visa => Some(c40_decode(
+                self.get_feature(0x01).or(self.get_feature(0x02))?,
+            )),
+            VdsDocumentType::EmergencyTravelDocument => Some(c40_decode(self.get_feature(0x02)?)),
+            VdsDocumentType::Unknown(..) => None,
+        }
+    }
+
+    /// Verifies the signature of the seal against the signer certificate in the trust store.
+    pub fn verify_signature(
+        &self,
+        trust_store: &trust_store::TrustStore,
+    ) -> Result<(), types::VerificationError> {
+        let signature = match self.signature.as_ref() {
+            Some(signature) => signature,
+            None => {
+                return Err(types::VerificationError {
+                    additional_text: "Seal has no signature zone.".to_string(),
+                });
+            }
+        };
+        // First two characters of the signer identifier are the ISO 3166-1 country code.
+        let certificate = trust_store
+            .find_certificate(
+                self.header.signer_identifier.get(0..2).unwrap_or_default(),
+                &self.header.certificate_reference,
+            )
+            .ok_or(types::VerificationError {
+                additional_text: format!(
+                    "No signer certificate for {} with reference {} in trust store.",
+                    self.header.signer_identifier, self.header.certificate_reference
+                ),
+            })?;
+        // A signer of one country can't vouch for a seal issued by another.
+        let certificate_country = trust_store::certificate_country(certificate);
+        if types::alpha2_country_code(&self.header.issuing_country)
+            != certificate_country.as_deref()
+        {
+            return Err(types::VerificationError {
+                additional_text: format!(
+                    "Seal was issued by {}, but its signer certificate is from {}.",
+                    self.header.issuing_country,
+                    certificate_country.unwrap_or("an unknown country".to_string())
+                ),
+            });
+        }
+        return trust_store::verify_ecdsa_signature(certificate, &self.signed_data, signature);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn c40_encode(text: &str) -> Vec<u8> {
+        let values: Vec<u16> = text
+            .bytes()
+            .map(|character| match character {
+                b'<' | b' ' => 3,
+                b'0'..=b'9' => (character - b'0') as u16 + 4,
+                _ => (character - b'A') as u16 + 14,
+            })
+            .collect();
+        let mut result: Vec<u8> = vec![];
+        for triplet in values.chunks(3) {
+            if triplet.len() == 1 {
+                result.extend_from_slice(&[0xFE, text.as_bytes()[text.len() - 1] + 1]);
+                continue;
+            }
+            let value = 1600 * triplet[0] + 40 * triplet[1] + triplet.get(2).unwrap_or(&0) + 1;
+            result.extend_from_slice(&value.to_be_bytes());
+        }
+        return result;
+    }
+
+    #[test]
+    fn c40_country_code_decoding() {
+        assert_eq!(c40_decode(&[0x6A, 0xBC]), "D<<");
+        assert_eq!(c40_decode(&c40_encode("UTO")), "UTO");
+        assert_eq!(c40_decode(&c40_encode("ABCD")), "ABCD");
+    }
+
+    #[test]
+    fn vds_visa_decoding() {
+        let mrz = "V<UTOERIKSSON<<ANNA<MARIA<<<<<<<<<<<<<<<<<<<L8988901C4XXX4009078F96121096";
+        let mut data: Vec<u8> = vec![VDS_MAGIC_CONSTANT, VDS_VERSION_4];
+        data.extend(c40_encode("UTO"));
+        data.extend(c40_encode("UTTS05"));
+        data.extend(c40_encode("12345"));
+        // 01.02.2023 and 03.02.2023, as MMDDYYYY integers
+        data.extend(&1_022_023u32.to_be_bytes()[1..]);
+        data.extend(&3_022_023u32.to_be_bytes()[1..]);
+        data.extend([0x5D, 0x01]);
+        let encoded_mrz = c40_encode(&mrz[..72]);
+        data.extend([0x01, encoded_mrz.len() as u8]);
+        data.extend(encoded_mrz);
+        data.extend([0x03, 0x01, 0x02]);
+        data.extend([0xFF, 0x04, 0xAA, 0xBB, 0xCC, 0xDD]);
+
+        let seal = VisibleDigitalSeal::deserialize(&data).unwrap();
+        assert_eq!(seal.header.version, 4);
+        assert_eq!(seal.header.issuing_country, "UTO");
+        assert_eq!(seal.header.signer_identifier, "UTTS");
+        assert_eq!(seal.header.certificate_reference, "12345");
+        assert_eq!(seal.header.document_issue_date.as_deref(), Some("20230102"));
+        assert_eq!(seal.document_type(), VdsDocumentType::Visa);
+        assert_eq!(seal.get_feature(0x03), Some(&vec![0x02]));
+        assert_eq!(seal.signature, Some(vec![0xAA, 0xBB, 0xCC, 0xDD]));
+        assert_eq!(seal.signed_data.len(), data.len() - 6);
+        match seal.mrz().unwrap() {
+            types::MRZ::MRVA(mrzobj) => {
+                assert_eq!(mrzobj.document_number, "L8988901C");
+                assert!(mrzobj.validate_check_digits(false).iter().all(|&val| val));
+            }
+            other => panic!("Expected MRV-A, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn vds_truncated_decoding() {
+        let mut data: Vec<u8> = vec![VDS_MAGIC_CONSTANT, VDS_VERSION_4];
+        data.extend(c40_encode("UTO"));
+        data.extend(c40_encode("UTTS05"));
+        data.extend(c40_encode("12345"));
+        data.extend(&1_022_023u32.to_be_bytes()[1..]);
+        data.extend(&3_022_023u32.to_be_bytes()[1..]);
+        data.extend([0x5D, 0x01]);
+        // Feature and signature lengths cut off right after the tag, and in the middle of the length field
+        for truncated_zone in [
+            vec![0x03],
+            vec![0x03, 0x82, 0x01],
+            vec![0xFF],
+            vec![0xFF, 0x81],
+        ] {
+            let truncated_data = [data.clone(), truncated_zone].concat();
+            assert!(VisibleDigitalSeal::deserialize(&truncated_data).is_none());
+        }
+        // Feature value that's shorter than its length
+        data.extend([0x03, 0x04, 0x01]);
+        assert!(VisibleDigitalSeal::deserialize(&data).is_none());
+    }
+}